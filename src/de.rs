@@ -1,80 +1,144 @@
-use std::fmt::{self, Display};
+use std::borrow::Cow;
 use std::str::FromStr;
 use std::mem::replace;
-use std::{error, io, num, result, str};
+use std::ops::Deref;
+use std::marker::PhantomData;
+use std::cell::RefCell;
+use std::{error, fmt, io, result, str};
 use serde::de::{self, Error as _, Deserialize, DeserializeOwned, DeserializeSeed, EnumAccess, Visitor, MapAccess, SeqAccess, VariantAccess, IntoDeserializer};
-use parse::{self, Item};
+use parse::{self, Item, Position};
 
-pub trait Trait {
-    fn next(&mut self) -> Option<result::Result<Item, Error>>;
+pub use error::{Error, ErrorKind};
+
+pub trait Trait<'de> {
+    fn next(&mut self) -> Option<result::Result<(Option<Position>, Item<'de>), Error>>;
 }
 
-impl<E, T: Iterator<Item=result::Result<Item, E>>> Trait for T where Error: From<E> {
-    fn next(&mut self) -> Option<result::Result<Item, Error>> {
-        Iterator::next(self).map(|v| v.map_err(Into::into))
+impl<'de, E, T: Iterator<Item=result::Result<Item<'de>, E>>> Trait<'de> for T where Error: From<E> {
+    fn next(&mut self) -> Option<result::Result<(Option<Position>, Item<'de>), Error>> {
+        Iterator::next(self).map(|v| v.map(|item| (None, item)).map_err(Into::into))
     }
 }
 
-#[derive(Debug, Clone)]
-pub enum Error {
-    /// Deserialization error
-    ///
-    /// Passed through error message from the type being deserialized.
-    Custom(String),
-
-    /// Internal consistency error
-    ///
-    /// Encountering this is probably misuse of the deserialization API or a bug in serde-ini.
-    UnexpectedEof,
-
-    /// Internal consistency error
-    ///
-    /// Encountering this is probably misuse of the deserialization API or a bug in serde-ini.
-    InvalidState,
+impl<'de, 'a: 'de, E, S: parse::Line<Item=Item<'a>>, T: Iterator<Item=result::Result<S, E>>> Trait<'de> for parse::Parser<T> where Error: From<parse::Error<E>> {
+    fn next(&mut self) -> Option<result::Result<(Option<Position>, Item<'de>), Error>> {
+        Iterator::next(self).map(|v| v.map(|(position, item)| (Some(position), item)).map_err(Into::into))
+    }
 }
 
-impl From<num::ParseIntError> for Error {
-    fn from(e: num::ParseIntError) -> Self {
-        Error::Custom(e.to_string())
-    }
+/// A string that either borrows from the input or lives only as long as the deserializer
+#[derive(Copy, Clone, Debug)]
+enum Reference<'a, 'de> {
+    Borrowed(&'de str),
+    Copied(&'a str),
 }
 
-impl From<num::ParseFloatError> for Error {
-    fn from(e: num::ParseFloatError) -> Self {
-        Error::Custom(e.to_string())
+impl<'a, 'de> Reference<'a, 'de> {
+    fn new(s: &'a Cow<'de, str>) -> Self {
+        match *s {
+            Cow::Borrowed(s) => Reference::Borrowed(s),
+            Cow::Owned(ref s) => Reference::Copied(s),
+        }
     }
-}
 
-impl<E: error::Error> From<parse::Error<E>> for Error {
-    fn from(e: parse::Error<E>) -> Self {
-        Error::Custom(e.to_string())
+    /// Narrows the string to the part of it selected by `f`.
+    fn map<F: for<'s> FnOnce(&'s str) -> Option<&'s str>>(self, f: F) -> Option<Self> {
+        match self {
+            Reference::Borrowed(s) => f(s).map(Reference::Borrowed),
+            Reference::Copied(s) => f(s).map(Reference::Copied),
+        }
     }
-}
 
-impl Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    /// Visits the string, as a borrowed string if it is borrowed from the input.
+    fn visit<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
         match self {
-            Error::Custom(msg) => write!(f, "{}", msg),
-            Error::UnexpectedEof => write!(f, "internal consistency error: unexpected EOF"),
-            Error::InvalidState => write!(f, "internal consistency error"),
+            Reference::Borrowed(s) => visitor.visit_borrowed_str(s),
+            Reference::Copied(s) => visitor.visit_str(s),
         }
     }
 }
 
-impl error::Error for Error {
-    fn description(&self) -> &str {
-        "INI deserialization error"
+impl<'a, 'de> Deref for Reference<'a, 'de> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match *self {
+            Reference::Borrowed(s) => s,
+            Reference::Copied(s) => s,
+        }
     }
 }
 
-impl de::Error for Error {
-    fn custom<T: Display>(msg: T) -> Self {
-        Error::Custom(msg.to_string())
-    }
+/// Parses a value or key with `FromStr`, describing it as `expected` if it is invalid.
+fn parse_str<T: FromStr>(value: &str, expected: &str) -> Result<T> where T::Err: error::Error + Send + Sync + 'static {
+    value.parse().map_err(|e| Error::invalid_value_from(e, value, expected))
 }
 
 pub type Result<T> = result::Result<T, Error>;
 
+/// How to treat a key that appears more than once within a section, or a map key that collides
+/// with another when case is ignored while serializing
+///
+/// Keys are compared ignoring case on both sides. A sequence collects every occurrence of its key
+/// unless the policy is `Error`; the others only differ where a single value is expected.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum DuplicateKeys {
+    /// Fail with an error that refers to both occurrences, even for a sequence
+    Error,
+    /// Use the first occurrence and ignore the rest, as the Windows profile API does
+    First,
+    /// Use the last occurrence and ignore the rest
+    Last,
+    /// Collect every occurrence as a sequence, failing if a struct field expected a single value
+    ///
+    /// The value of a map takes the last occurrence instead, as it did before policies existed.
+    #[default]
+    Collect,
+}
+
+/// Returns whether two keys are the same when case is ignored.
+pub(crate) fn same_key(a: &str, b: &str) -> bool {
+    a.chars().flat_map(char::to_lowercase).eq(b.chars().flat_map(char::to_lowercase))
+}
+
+/// A key or section that the type being deserialized has no field for
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct UnknownItem {
+    /// The name of the section, or of the section containing the key
+    pub section: Option<String>,
+    /// The full name of the key, or `None` if the item is a section
+    pub key: Option<String>,
+    pub position: Option<Position>,
+    /// The fields that the type would have accepted
+    pub expected: &'static [&'static str],
+    /// The most similar field name, if any is close enough to be a likely misspelling
+    pub suggestion: Option<&'static str>,
+}
+
+/// Returns the field most similar to `name`, if it is within a third of its length in edits.
+fn suggest(name: &str, fields: &[&'static str]) -> Option<&'static str> {
+    fn distance(a: &str, b: &str) -> usize {
+        let b: Vec<char> = b.chars().collect();
+        let mut row: Vec<usize> = (0..=b.len()).collect();
+        for (i, a) in a.chars().enumerate() {
+            let mut diagonal = row[0];
+            row[0] = i + 1;
+            for (j, &b) in b.iter().enumerate() {
+                let substitution = diagonal + if a.eq_ignore_ascii_case(&b) { 0 } else { 1 };
+                diagonal = row[j + 1];
+                row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+            }
+        }
+        row[b.len()]
+    }
+
+    fields.iter()
+        .map(|&field| (distance(name, field), field))
+        .filter(|&(distance, _)| distance <= name.chars().count().div_ceil(3))
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, field)| field)
+}
+
 enum PeekKind {
     Value,
     Section,
@@ -87,35 +151,531 @@ enum Next<T> {
     Some(T),
 }
 
+#[derive(Debug, Clone)]
+struct Config {
+    truthy: Vec<String>,
+    falsy: Vec<String>,
+    list_separator: Option<char>,
+    section_separator: Option<char>,
+    key_separator: Option<char>,
+    infer_types: bool,
+    merge_sections: bool,
+    section_name_key: Option<String>,
+    duplicate_keys: DuplicateKeys,
+    /// Every unknown item found so far, if they are being tracked
+    unknown: Option<RefCell<Vec<UnknownItem>>>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            truthy: ["true", "yes", "on", "1"].iter().map(|s| s.to_string()).collect(),
+            falsy: ["false", "no", "off", "0"].iter().map(|s| s.to_string()).collect(),
+            list_separator: None,
+            section_separator: None,
+            key_separator: None,
+            infer_types: false,
+            merge_sections: false,
+            section_name_key: None,
+            duplicate_keys: Default::default(),
+            unknown: None,
+        }
+    }
+}
+
+impl Config {
+    fn parse_bool(&self, value: &str) -> Result<bool> {
+        if self.truthy.iter().any(|v| v.eq_ignore_ascii_case(value)) {
+            Ok(true)
+        } else if self.falsy.iter().any(|v| v.eq_ignore_ascii_case(value)) {
+            Ok(false)
+        } else {
+            Err(Error::invalid_value(de::Unexpected::Str(value), &"a boolean"))
+        }
+    }
+
+    /// Returns the value as the type inferred for it when there is no type hint.
+    fn infer<'v>(&self, value: &'v str) -> de::Unexpected<'v> {
+        if !self.infer_types {
+            return de::Unexpected::Str(value)
+        }
+
+        if let Ok(v) = value.parse() {
+            de::Unexpected::Unsigned(v)
+        } else if let Ok(v) = value.parse() {
+            de::Unexpected::Signed(v)
+        } else {
+            match (value.parse::<f64>(), self.parse_bool(value)) {
+                (Ok(v), _) if v.is_finite() => de::Unexpected::Float(v),
+                (_, Ok(v)) => de::Unexpected::Bool(v),
+                _ => de::Unexpected::Str(value),
+            }
+        }
+    }
+
+    /// Attributes an error that a visitor raised after buffering the values of `entries`, as for
+    /// a flattened struct or an internally tagged enum, to the one entry whose value it describes.
+    fn attribute_buffered(&self, entries: &[Entry], e: Error) -> Error {
+        let found = match *e.kind() {
+            ErrorKind::InvalidValue { ref found, .. } if e.key().is_none() && e.section().is_none() => found,
+            _ => return e,
+        };
+        let mut matches = entries.iter()
+            .filter(|entry| entry.consumed && self.infer(&entry.value).to_string() == *found);
+        match (matches.next(), matches.next()) {
+            (Some(entry), None) => entry.attribute(e),
+            _ => e,
+        }
+    }
+
+    /// Returns the key that a section called `name` is found under, relative to `prefix`.
+    ///
+    /// `prefix` is `None` at the top level of the document, where every section is a key unless
+    /// nesting is enabled, in which case only the first component of the name is.
+    fn section_key<'s>(&self, name: &'s str, prefix: Option<&str>) -> Option<&'s str> {
+        match (self.section_separator, prefix) {
+            (None, None) => Some(name),
+            (None, Some(..)) => None,
+            (Some(separator), None) => name.split(separator).next(),
+            (Some(separator), Some(prefix)) => {
+                if name.len() > prefix.len() && name.starts_with(prefix) && name[prefix.len()..].starts_with(separator) {
+                    name[prefix.len() + separator.len_utf8()..].split(separator).next()
+                } else {
+                    None
+                }
+            },
+        }
+    }
+
+    /// Returns the component of `key` that follows `prefix`, and whether more components remain.
+    fn entry_key<'s>(&self, key: &'s str, prefix: &str) -> Option<(&'s str, bool)> {
+        match self.key_separator {
+            Some(separator) if key.starts_with(prefix) => {
+                let key = &key[prefix.len()..];
+                Some(match key.find(separator) {
+                    Some(i) => (&key[..i], true),
+                    None => (key, false),
+                })
+            },
+            Some(..) => None,
+            None if prefix.is_empty() => Some((key, false)),
+            None => None,
+        }
+    }
+
+    /// Returns the prefix shared by the keys nested alongside `key`, if it has more components
+    /// following `prefix`.
+    fn nested_key_prefix(&self, key: &str, prefix: &str) -> Option<String> {
+        match (self.entry_key(key, prefix), self.key_separator) {
+            (Some((key, true)), Some(separator)) => Some(format!("{}{}{}", prefix, key, separator)),
+            _ => None,
+        }
+    }
+
+    /// Records a key or section called `name` as unknown if `fields` are known and do not include it.
+    fn check_field<F: FnOnce(&'static [&'static str], Option<&'static str>) -> UnknownItem>(&self, fields: Option<&'static [&'static str]>, name: &str, item: F) {
+        if let (Some(unknown), Some(fields)) = (self.unknown.as_ref(), fields) {
+            if !fields.contains(&name) {
+                unknown.borrow_mut().push(item(fields, suggest(name, fields)));
+            }
+        }
+    }
+
+    /// Returns the full name of the section found under `key`, relative to `prefix`.
+    fn section_name(&self, key: &str, prefix: Option<&str>) -> String {
+        match (self.section_separator, prefix) {
+            (Some(separator), Some(prefix)) => format!("{}{}{}", prefix, separator, key),
+            _ => key.to_owned(),
+        }
+    }
+
+    /// Deserializes the value of `entries[0]`, attributing any error to it.
+    ///
+    /// Later occurrences of the same key in `entries` are collected by a sequence, or handled
+    /// according to `duplicate_keys` where a single value is expected. Set `map` for the value of
+    /// a map rather than of a struct field.
+    fn deserialize_value<'de, S: DeserializeSeed<'de>>(&self, entries: &mut [Entry<'de>], map: bool, seed: S) -> Result<S::Value> {
+        fn duplicate(entries: &[Entry]) -> Option<usize> {
+            entries[1..].iter().position(|entry| !entry.consumed && same_key(&entry.key, &entries[0].key)).map(|i| i + 1)
+        }
+
+        fn duplicate_error(entries: &[Entry], i: usize) -> Error {
+            entries[i].attribute(ErrorKind::DuplicateKey {
+                key: entries[0].key.clone().into_owned(),
+                first: entries[0].position,
+            }.into())
+        }
+
+        if self.duplicate_keys == DuplicateKeys::Error {
+            if let Some(i) = duplicate(entries) {
+                return Err(duplicate_error(entries, i))
+            }
+        }
+
+        let mut deserializer = ValueDeserializer {
+            config: self,
+            entries: &mut *entries,
+            map,
+            chosen: 0,
+        };
+        let value = seed.deserialize(&mut deserializer);
+        let chosen = deserializer.chosen;
+        let value = value.map_err(|e| entries[chosen].attribute(e))?;
+
+        // any repetitions left over were not collected into a sequence
+        match duplicate(entries) {
+            Some(i) => Err(duplicate_error(entries, i)),
+            None => Ok(value),
+        }
+    }
+
+    /// Deserializes every key below `prefix` as a map.
+    fn deserialize_keys<'de, S: DeserializeSeed<'de>>(&self, entries: &mut Entries<'de>, prefix: String, seed: S) -> Result<S::Value> {
+        let position = entries.entries.iter()
+            .find(|entry| !entry.consumed && entry.key.starts_with(&prefix))
+            .and_then(|entry| entry.position);
+        let value = seed.deserialize(&mut KeysDeserializer {
+            config: self,
+            prefix: &prefix,
+            entries,
+        }).map_err(|e| e.at(position).in_key(prefix.trim_end_matches(|c| Some(c) == self.key_separator), None))?;
+
+        // mark any nested keys the visitor did not ask for as consumed
+        for entry in entries.entries.iter_mut().filter(|entry| entry.key.starts_with(&prefix)) {
+            entry.consumed = true;
+        }
+
+        Ok(value)
+    }
+
+    /// Returns the key of the next section below `prefix`, along with the first header found under it.
+    fn next_section_key<'s, 'de>(&self, sections: &'s [Section<'de>], prefix: Option<&str>) -> Option<(&'s Section<'de>, Reference<'s, 'de>)> {
+        sections.iter()
+            .filter(|section| !section.consumed)
+            .filter_map(|section| Reference::new(&section.name).map(|name| self.section_key(name, prefix)).map(|key| (section, key)))
+            .next()
+    }
+
+    /// Deserializes the next section below `prefix`, including any sections nested within it.
+    ///
+    /// An `element` of a top-level sequence can also be deserialized along with its name.
+    fn deserialize_section<'de, S: DeserializeSeed<'de>>(&self, sections: &mut [Section<'de>], prefix: Option<&str>, element: bool, seed: S) -> Result<Option<S::Value>> {
+        let name = match self.next_section_key(sections, prefix) {
+            Some((_, key)) => self.section_name(&key, prefix),
+            None => return Ok(None),
+        };
+
+        let mut entries = Vec::new();
+        // the index of each section the entries were taken from, and how many it had
+        let mut taken = Vec::new();
+        let mut position = None;
+        for (i, section) in sections.iter_mut().enumerate().filter(|(_, section)| !section.consumed && section.name == name) {
+            section.consumed = true;
+            position = position.or(section.position);
+            taken.push((i, section.entries.entries.len()));
+            entries.append(&mut section.entries.entries);
+            if element || (self.section_separator.is_none() && !self.merge_sections) {
+                // every header is a distinct element of a sequence, or without nesting a distinct key
+                break
+            }
+        }
+
+        let unknown = self.unknown.as_ref().map(|unknown| unknown.borrow().len());
+        let mut entries = Entries::new(entries);
+        let value = seed.deserialize(&mut SectionDeserializer {
+            config: self,
+            name: &name,
+            element,
+            entries: &mut entries,
+            sections,
+        });
+
+        let value = value.map_err(|e| self.attribute_buffered(&entries.entries, e));
+
+        // return the entries to their sections, so the document can be deserialized again
+        let mut entries = entries.entries.into_iter();
+        for (i, len) in taken {
+            sections[i].entries.entries = entries.by_ref().take(len).collect();
+        }
+        let value = value.map_err(|e| e.at(position).in_section(&name))?;
+
+        // attribute unknown nested keys to the section, as with errors
+        if let (Some(unknown), Some(len)) = (self.unknown.as_ref(), unknown) {
+            for item in unknown.borrow_mut()[len..].iter_mut().filter(|item| item.section.is_none()) {
+                item.section = Some(name.clone());
+            }
+        }
+
+        // mark any nested sections the visitor did not ask for as consumed
+        for section in sections.iter_mut().filter(|section| !section.consumed) {
+            if self.section_key(&section.name, Some(&name)).is_some() {
+                section.consumed = true;
+            }
+        }
+
+        Ok(Some(value))
+    }
+}
+
+/// A `key=value` line buffered from the body of a section
+#[derive(Debug, Clone)]
+struct Entry<'de> {
+    key: Cow<'de, str>,
+    value: Cow<'de, str>,
+    position: Option<Position>,
+    /// The number of the entry or section header that precedes it in the document
+    index: usize,
+    /// Set once the entry has been handed out, either on its own or as part of a sequence
+    consumed: bool,
+}
+
+impl<'de> Entry<'de> {
+    /// Attributes an error to the entry, unless it is already attributed to one nested within it.
+    fn attribute(&self, e: Error) -> Error {
+        e.at(self.position).in_key(&self.key, Some(&self.value[..])).in_item(self.index)
+    }
+}
+
 #[derive(Debug)]
-pub struct Deserializer<T> {
+struct Entries<'de> {
+    entries: Vec<Entry<'de>>,
+    pos: usize,
+}
+
+impl<'de> Entries<'de> {
+    fn new(entries: Vec<Entry<'de>>) -> Self {
+        Entries {
+            entries,
+            pos: 0,
+        }
+    }
+
+    fn peek(&mut self) -> Option<&Entry<'de>> {
+        while let Some(entry) = self.entries.get(self.pos) {
+            if entry.consumed {
+                self.pos += 1;
+            } else {
+                return Some(&self.entries[self.pos])
+            }
+        }
+
+        None
+    }
+
+    fn reset(&mut self) {
+        self.pos = 0;
+        for entry in &mut self.entries {
+            entry.consumed = false;
+        }
+    }
+
+    fn peek_key(&mut self) -> Option<&str> {
+        self.peek().map(|entry| &entry.key[..])
+    }
+
+    /// Consumes the entry returned by `peek_key`, followed by the rest of the section.
+    fn next_entries(&mut self) -> Result<&mut [Entry<'de>]> {
+        let pos = self.pos;
+        match self.entries.get_mut(pos) {
+            Some(entry) => entry.consumed = true,
+            None => return Err(ErrorKind::InvalidState.into()),
+        }
+        self.pos += 1;
+        Ok(&mut self.entries[pos..])
+    }
+}
+
+/// A section header buffered along with its body
+#[derive(Debug)]
+struct Section<'de> {
+    name: Cow<'de, str>,
+    /// The position of the first header, when several are combined
+    position: Option<Position>,
+    /// The number of entries and section headers that precede it in the document
+    index: usize,
+    entries: Entries<'de>,
+    consumed: bool,
+}
+
+impl<'de> Section<'de> {
+    /// Attributes an error in the name of the section to its header.
+    fn attribute(&self, e: Error) -> Error {
+        e.at(self.position).in_section(&self.name).in_item(self.index)
+    }
+}
+
+#[derive(Debug)]
+pub struct Deserializer<'de, T> {
     input: T,
-    next: Next<Result<Item>>,
+    next: Next<Result<(Option<Position>, Item<'de>)>>,
+    config: Config,
+    file: Option<String>,
+    /// The number of entries and section headers buffered so far
+    read: usize,
+    /// Syntax errors skipped over while collecting errors
+    errors: Option<Vec<Error>>,
+    on_unknown: Option<OnUnknown<'de>>,
+}
+
+/// What to do with unknown items once the document has been deserialized
+enum OnUnknown<'de> {
+    Deny,
+    Report(Box<dyn FnMut(Vec<UnknownItem>) + 'de>),
+}
+
+impl<'de> fmt::Debug for OnUnknown<'de> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            OnUnknown::Deny => write!(f, "Deny"),
+            OnUnknown::Report(..) => write!(f, "Report(..)"),
+        }
+    }
 }
 
-impl<T> Deserializer<T> {
+impl<'de, T> Deserializer<'de, T> {
     pub fn new(input: T) -> Self {
         Deserializer {
-            input: input,
+            input,
             next: Next::Init,
+            config: Default::default(),
+            file: None,
+            read: 0,
+            errors: None,
+            on_unknown: None,
         }
     }
+
+    /// Sets the spellings accepted for `true` and `false` when deserializing a `bool`.
+    ///
+    /// Values are compared case-insensitively. By default `true`, `yes`, `on` and `1` are truthy,
+    /// while `false`, `no`, `off` and `0` are falsy.
+    pub fn bool_values<S: AsRef<str>>(mut self, truthy: &[S], falsy: &[S]) -> Self {
+        self.config.truthy = truthy.iter().map(|s| s.as_ref().into()).collect();
+        self.config.falsy = falsy.iter().map(|s| s.as_ref().into()).collect();
+        self
+    }
+
+    /// Splits values on `separator` when deserializing a sequence, as in `hosts = a, b, c`.
+    ///
+    /// Elements are trimmed, and may be quoted or escaped as described by `parse::split_list`.
+    /// Repeated keys are still collected, with each occurrence contributing its own elements.
+    pub fn list_separator(mut self, separator: char) -> Self {
+        self.config.list_separator = Some(separator);
+        self
+    }
+
+    /// Treats section names containing `separator` as nested sections.
+    ///
+    /// With a separator of `.`, the keys of `[server.tls]` are deserialized into the `tls` field of
+    /// the `server` section. Parent sections do not need a header of their own, and repeated
+    /// headers for the same section are combined.
+    pub fn section_separator(mut self, separator: char) -> Self {
+        self.config.section_separator = Some(separator);
+        self
+    }
+
+    /// Treats keys containing `separator` as nested maps within their section.
+    ///
+    /// With a separator of `.`, the key `db.pool.max` is deserialized into the `max` field of the
+    /// `pool` field of the `db` field of its section.
+    pub fn key_separator(mut self, separator: char) -> Self {
+        self.config.key_separator = Some(separator);
+        self
+    }
+
+    /// Infers the type of values deserialized without a type hint, which are otherwise strings.
+    ///
+    /// Values that parse as an integer, a finite float or a `bool` are visited as one, so that
+    /// untagged enums can match numeric variants and types such as `serde_json::Value` see
+    /// numbers. It is also needed to read back numeric fields of internally tagged enums and
+    /// flattened structs, whose string fields will then reject values such as `007` or `yes`.
+    pub fn infer_types(mut self, infer: bool) -> Self {
+        self.config.infer_types = infer;
+        self
+    }
+
+    /// Combines every section with the same name into one, as if their keys followed a single
+    /// header.
+    ///
+    /// Keys repeated across the combined sections are treated as set by `duplicate_keys`, so that
+    /// `DuplicateKeys::Last` lets later sections override earlier ones. Sections are always
+    /// combined when nested with `section_separator`, but never when they are the elements of a
+    /// top-level sequence.
+    pub fn merge_sections(mut self, merge: bool) -> Self {
+        self.config.merge_sections = merge;
+        self
+    }
+
+    /// Visits the name of each section in a top-level sequence as a key called `key`, before the
+    /// keys of its body.
+    ///
+    /// This lets sections be deserialized in order into a `Vec` of structs that hold their own
+    /// name, as in `struct Rule { name: String, #[serde(flatten)] body: Body }`. Sections can be
+    /// deserialized as `(name, body)` tuples without it.
+    pub fn section_name_key<S: Into<String>>(mut self, key: S) -> Self {
+        self.config.section_name_key = Some(key.into());
+        self
+    }
+
+    /// Sets how a key that appears more than once within a section is deserialized.
+    ///
+    /// By default, repeated keys are collected as a sequence, the value of a map takes the last
+    /// occurrence, and a struct field that expects a single value fails.
+    pub fn duplicate_keys(mut self, policy: DuplicateKeys) -> Self {
+        self.config.duplicate_keys = policy;
+        self
+    }
+
+    /// Fails if the document contains a key or section that the type being deserialized has no
+    /// field for.
+    ///
+    /// Unlike `#[serde(deny_unknown_fields)]`, this applies to every struct within the type, and the
+    /// error suggests the field that was most likely meant. Maps, and structs with flattened fields,
+    /// accept any key and are not checked.
+    pub fn deny_unknown(mut self) -> Self {
+        self.config.unknown = Some(Default::default());
+        self.on_unknown = Some(OnUnknown::Deny);
+        self
+    }
+
+    /// Calls `report` with every key or section that the type being deserialized has no field for,
+    /// instead of failing as `deny_unknown` does.
+    ///
+    /// It is called once the document has been deserialized, and only if there are any.
+    pub fn report_unknown<F: FnMut(Vec<UnknownItem>) + 'de>(mut self, report: F) -> Self {
+        self.config.unknown = Some(Default::default());
+        self.on_unknown = Some(OnUnknown::Report(Box::new(report)));
+        self
+    }
+
+    /// Names the file being deserialized, for errors to refer to.
+    pub fn file_name<S: Into<String>>(mut self, name: S) -> Self {
+        self.file = Some(name.into());
+        self
+    }
 }
 
-impl<T: Trait> Deserializer<T> {
+impl<'de, T: Trait<'de>> Deserializer<'de, T> {
     fn populate(&mut self) {
         while let Next::Init = self.next {
             let next = self.input.next();
             self.next = match next {
-                Some(Ok(Item::Comment { .. })) => Next::Init,
-                Some(Ok(Item::Empty)) => Next::Init,
+                Some(Ok((_, Item::Comment { .. }))) => Next::Init,
+                Some(Ok((_, Item::Empty))) => Next::Init,
+                Some(Err(e)) => match (e.kind(), &mut self.errors) {
+                    (&ErrorKind::Syntax(..), &mut Some(ref mut errors)) => {
+                        errors.push(e);
+                        Next::Init
+                    },
+                    _ => Next::Some(Err(e)),
+                },
                 Some(v) => Next::Some(v),
                 None => Next::Eof,
             };
         }
     }
 
-    fn next_item(&mut self) -> Result<Item> {
+    fn next_item(&mut self) -> Result<(Option<Position>, Item<'de>)> {
         let next = match self.next {
             Next::Eof | Next::Some(Err(..)) => Next::Eof,
             _ => Next::Init,
@@ -123,14 +683,14 @@ impl<T: Trait> Deserializer<T> {
         let next = replace(&mut self.next, next);
         match next {
             Next::Some(v) => v,
-            Next::Eof => Err(Error::UnexpectedEof),
+            Next::Eof => Err(ErrorKind::UnexpectedEof.into()),
             Next::Init => unreachable!(),
         }
     }
 
-    fn peek_item(&mut self) -> Result<Option<&mut Item>> {
+    fn peek_item(&mut self) -> Result<Option<&mut Item<'de>>> {
         match &mut self.next {
-            &mut Next::Some(Ok(ref mut v)) => Ok(Some(v)),
+            &mut Next::Some(Ok((_, ref mut v))) => Ok(Some(v)),
             e @ &mut Next::Some(Err(..)) => {
                 if let Next::Some(Err(e)) = replace(e, Next::Eof) {
                     Err(e)
@@ -153,58 +713,433 @@ impl<T: Trait> Deserializer<T> {
         })
     }
 
-    fn peek_section(&mut self) -> Result<&str> {
+    /// Buffers every value up until the next section header.
+    fn next_entries(&mut self) -> Result<Entries<'de>> {
+        let mut entries = Vec::new();
+        while let Some(PeekKind::Value) = self.peek_kind()? {
+            match self.next_item()? {
+                (position, Item::Value { key, value }) => {
+                    entries.push(Entry {
+                        key,
+                        value,
+                        position,
+                        index: self.read,
+                        consumed: false,
+                    });
+                    self.read += 1;
+                },
+                _ => return Err(ErrorKind::InvalidState.into()),
+            }
+        }
+
+        Ok(Entries::new(entries))
+    }
+
+    /// Buffers every remaining section in the document.
+    fn next_sections(&mut self) -> Result<Vec<Section<'de>>> {
+        let mut sections = Vec::new();
+        while let Some(PeekKind::Section) = self.peek_kind()? {
+            let (position, name) = self.next_section()?;
+            let index = self.read;
+            self.read += 1;
+            sections.push(Section {
+                name,
+                position,
+                index,
+                entries: self.next_entries()?,
+                consumed: false,
+            });
+        }
+
+        Ok(sections)
+    }
+
+    fn next_section(&mut self) -> Result<(Option<Position>, Cow<'de, str>)> {
         self.populate();
-        match self.peek_item()? {
-            Some(&mut Item::Section { ref name }) => Ok(name),
-            Some(..) => Err(Error::InvalidState),
-            None => Err(Error::UnexpectedEof),
+        match self.next_item()? {
+            (position, Item::Section { name }) => Ok((position, name)),
+            _ => Err(ErrorKind::InvalidState.into()),
         }
     }
 
-    fn peek_key(&mut self) -> Result<&str> {
+    fn assert_eof(&mut self) -> Result<()> {
         self.populate();
-        match self.peek_item()? {
-            Some(&mut Item::Value { ref key, .. }) => Ok(key),
-            Some(..) => Err(Error::InvalidState),
-            None => Err(Error::UnexpectedEof),
+        let position = match self.next {
+            Next::Some(Ok((position, _))) => position,
+            _ => None,
+        };
+        let result = match self.peek_item() {
+            Ok(Some(..)) => Err(Error::from(ErrorKind::TrailingData).at(position)),
+            Ok(None) => Ok(()),
+            Err(e) => Err(e),
+        };
+        result.map_err(|e| e.in_file(self.file.as_ref().map(|s| &s[..])))
+    }
+
+    /// Reports the unknown items found while deserializing the document, returning them as errors
+    /// if they are denied.
+    fn finish_unknown(&mut self) -> Vec<Error> {
+        let unknown = match self.config.unknown {
+            Some(ref unknown) => unknown.take(),
+            None => return Vec::new(),
+        };
+        match self.on_unknown {
+            Some(OnUnknown::Report(ref mut report)) => {
+                if !unknown.is_empty() {
+                    report(unknown);
+                }
+                Vec::new()
+            },
+            _ => unknown.into_iter().map(Into::into).collect(),
+        }
+    }
+
+    /// Deserializes the whole document with `f`, failing if it contains any denied unknown items.
+    fn deserialize_document<R, F: FnOnce(BufferedDocument<'_, 'de>) -> Result<R>>(&mut self, f: F) -> Result<R> {
+        let result = self.next_document().and_then(f);
+        let result = result.and_then(|value| match self.finish_unknown().into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(value),
+        });
+        result.map_err(|e| e.in_file(self.file.as_ref().map(|s| &s[..])))
+    }
+
+    fn next_document(&mut self) -> Result<BufferedDocument<'_, 'de>> {
+        let entries = self.next_entries()?;
+        let sections = self.next_sections()?;
+        Ok(BufferedDocument {
+            config: &self.config,
+            entries,
+            sections,
+        })
+    }
+
+    /// Deserializes the document, collecting every error instead of stopping at the first.
+    ///
+    /// Lines with syntax errors are skipped, as are keys and sections whose name or value fails to
+    /// deserialize, so that as much of the value is returned as its type allows. It is `None` if a
+    /// field without a default is missing or skipped, or an error could not be traced to a single
+    /// key or section. Errors are ordered by their position in the input.
+    pub fn deserialize_collecting<V: DeserializeOwned>(&mut self) -> (Option<V>, Vec<Error>) {
+        self.errors = Some(Vec::new());
+        let document = self.next_document().map(|document| (document.entries, document.sections));
+        let mut errors = self.errors.take().unwrap_or_default();
+
+        let mut value = None;
+        if let Ok((entries, sections)) = document.map_err(|e| errors.push(e)) {
+            let mut document = BufferedDocument {
+                config: &self.config,
+                entries,
+                sections,
+            };
+            // the section and field name of every item skipped so far
+            let mut skipped: Vec<(Option<String>, String)> = Vec::new();
+            loop {
+                if let Some(ref unknown) = self.config.unknown {
+                    unknown.borrow_mut().clear();
+                }
+                document.reset();
+                let e = match V::deserialize(&mut document) {
+                    Ok(v) => {
+                        value = Some(v);
+                        break
+                    },
+                    Err(e) => e,
+                };
+
+                if !e.item().map(|index| skip_item(&mut document.entries, &mut document.sections, index)).unwrap_or(false) {
+                    // a required field that was skipped has already been reported
+                    let redundant = match *e.kind() {
+                        ErrorKind::MissingField(field) => skipped.iter()
+                            .any(|(section, name)| section.as_deref() == e.section() && name == field),
+                        _ => false,
+                    };
+                    if !redundant {
+                        errors.push(e);
+                    }
+                    break
+                }
+
+                skipped.push(match (e.key(), self.config.key_separator) {
+                    (Some(key), Some(separator)) => (e.section().map(Into::into), key.rsplit(separator).next().unwrap_or(key).into()),
+                    (Some(key), None) => (e.section().map(Into::into), key.into()),
+                    (None, _) => (None, e.section().unwrap_or_default().into()),
+                });
+                errors.push(e);
+            }
+        }
+        errors.extend(self.finish_unknown());
+
+        let file = self.file.as_ref().map(|s| &s[..]);
+        let mut errors: Vec<_> = errors.into_iter().map(|e| e.in_file(file)).collect();
+        errors.sort_by_key(|e| (e.position().is_none(), e.position()));
+        (value, errors)
+    }
+}
+
+/// Removes the entry or section numbered `index` from a buffered document, returning whether it was found.
+fn skip_item(entries: &mut Entries, sections: &mut Vec<Section>, index: usize) -> bool {
+    let count = |entries: &Entries, sections: &[Section]| {
+        entries.entries.len() + sections.iter().map(|section| 1 + section.entries.entries.len()).sum::<usize>()
+    };
+    let before = count(entries, sections);
+    entries.entries.retain(|entry| entry.index != index);
+    sections.retain(|section| section.index != index);
+    for section in sections.iter_mut() {
+        section.entries.entries.retain(|entry| entry.index != index);
+    }
+
+    count(entries, sections) != before
+}
+
+impl<'de, T: Trait<'de>> de::Deserializer<'de> for &mut Deserializer<'de, T> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_document(|mut document| document.deserialize_any(visitor))
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_document(|mut document| document.deserialize_seq(visitor))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        self.deserialize_document(|mut document| document.deserialize_struct("", fields, visitor))
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes
+        byte_buf unit unit_struct newtype_struct tuple tuple_struct
+        map identifier ignored_any enum
+    }
+}
+
+impl<'de, R: io::BufRead> Deserializer<'de, parse::Parser<io::Lines<R>>> {
+    /// Creates an INI deserializer from an `io::BufRead`.
+    pub fn from_bufread(reader: R) -> Self {
+        Deserializer::new(parse::Parser::from_bufread(reader))
+    }
+}
+
+impl<'de, R: io::Read> Deserializer<'de, parse::Parser<io::Lines<io::BufReader<R>>>> {
+    /// Creates an INI deserializer from a reader.
+    pub fn from_read(reader: R) -> Self {
+        Deserializer::new(parse::Parser::from_read(reader))
+    }
+}
+
+impl<'de> Deserializer<'de, parse::Parser<parse::OkIter<str::Lines<'de>>>> {
+    /// Creates an INI deserializer from a `&str`, whose values can be borrowed from it.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(s: &'de str) -> Self {
+        Deserializer::new(parse::Parser::from_str(s))
+    }
+}
+
+/// Deserializes a whole document that has been read into memory
+struct BufferedDocument<'a, 'de> {
+    config: &'a Config,
+    entries: Entries<'de>,
+    sections: Vec<Section<'de>>,
+}
+
+impl<'a, 'de> BufferedDocument<'a, 'de> {
+    /// Marks every entry and section as not yet consumed, to deserialize the document again.
+    fn reset(&mut self) {
+        self.entries.reset();
+        for section in &mut self.sections {
+            section.consumed = false;
+            section.entries.reset();
+        }
+    }
+}
+
+impl<'de, 'a, 'b> de::Deserializer<'de> for &'b mut BufferedDocument<'a, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_map(MapAccessSectionBody {
+            config: self.config,
+            prefix: None,
+            fields: None,
+            name_key: None,
+            entries: &mut self.entries,
+            sections: &mut self.sections,
+        }).map_err(|e| self.config.attribute_buffered(&self.entries.entries, e))
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        visitor.visit_map(MapAccessSectionBody {
+            config: self.config,
+            prefix: None,
+            fields: Some(fields),
+            name_key: None,
+            entries: &mut self.entries,
+            sections: &mut self.sections,
+        })
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(SeqAccessTop {
+            config: self.config,
+            entries: &mut self.entries,
+            sections: &mut self.sections,
+        })
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes
+        byte_buf unit unit_struct newtype_struct tuple tuple_struct
+        map identifier ignored_any enum
+    }
+}
+
+/// Deserializes a section body along with any sections nested below it
+pub struct SectionDeserializer<'a, 'de> {
+    config: &'a Config,
+    name: &'a str,
+    /// Set for a section that is an element of a top-level sequence
+    element: bool,
+    entries: &'a mut Entries<'de>,
+    sections: &'a mut [Section<'de>],
+}
+
+impl<'a, 'de> SectionDeserializer<'a, 'de> {
+    fn visit_body<V: Visitor<'de>>(&mut self, fields: Option<&'static [&'static str]>, visitor: V) -> Result<V::Value> {
+        let name_key = match self.config.section_name_key {
+            Some(ref key) if self.element => Some(&key[..]),
+            _ => None,
+        };
+        visitor.visit_map(MapAccessSectionBody {
+            config: self.config,
+            prefix: Some(self.name),
+            fields,
+            name_key,
+            entries: self.entries,
+            sections: self.sections,
+        })
+    }
+}
+
+impl<'de, 'a> de::Deserializer<'de> for &'a mut SectionDeserializer<'a, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.visit_body(None, visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        self.visit_body(Some(fields), visitor)
+    }
+
+    /// A pair of the name of the section and its body.
+    fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        struct SeqAccessNamed<'a, 'de> {
+            section: Option<&'a mut SectionDeserializer<'a, 'de>>,
+            name: bool,
+        }
+
+        impl<'de, 'a> SeqAccess<'de> for SeqAccessNamed<'a, 'de> {
+            type Error = Error;
+
+            fn next_element_seed<E>(&mut self, seed: E) -> Result<Option<E::Value>>
+            where
+                E: DeserializeSeed<'de>,
+            {
+                if self.name {
+                    self.name = false;
+                    let section = self.section.as_ref().ok_or(ErrorKind::InvalidState)?;
+                    return seed.deserialize(KeyDeserializer { config: section.config, key: Reference::Copied(section.name) }).map(Some)
+                }
+
+                match self.section.take() {
+                    Some(section) => seed.deserialize(section).map(Some),
+                    None => Ok(None),
+                }
+            }
+
+            fn size_hint(&self) -> Option<usize> {
+                Some(self.section.as_ref().map(|_| if self.name { 2 } else { 1 }).unwrap_or(0))
+            }
+        }
+
+        visitor.visit_seq(SeqAccessNamed {
+            section: Some(self),
+            name: true,
+        })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, len: usize, visitor: V) -> Result<V::Value> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        struct EnumAccessSection<'a, 'de>(&'a mut SectionDeserializer<'a, 'de>);
+
+        impl<'de, 'a> EnumAccess<'de> for EnumAccessSection<'a, 'de> {
+            type Error = Error;
+            type Variant = VariantAccessSection<'a, 'de>;
+
+            fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
+            where
+                V: DeserializeSeed<'de>,
+            {
+                let name: de::value::StrDeserializer<Error> = self.0.name.into_deserializer();
+                let variant = seed.deserialize(name)?;
+                Ok((variant, VariantAccessSection(self.0)))
+            }
         }
-    }
 
-    fn next_value(&mut self) -> Result<String> {
-        self.populate();
-        match self.next_item()? {
-            Item::Value { value, .. } => Ok(value),
-            _ => Err(Error::InvalidState),
-        }
-    }
+        struct VariantAccessSection<'a, 'de>(&'a mut SectionDeserializer<'a, 'de>);
 
-    fn next_section(&mut self) -> Result<String> {
-        self.populate();
-        match self.next_item()? {
-            Item::Section { name } => Ok(name),
-            _ => Err(Error::InvalidState),
-        }
-    }
+        impl<'de, 'a> VariantAccess<'de> for VariantAccessSection<'a, 'de> {
+            type Error = Error;
 
-    fn assert_eof(&mut self) -> Result<()> {
-        self.populate();
-        match self.peek_item()? {
-            Some(..) => Err(Error::InvalidState),
-            None => Ok(()),
-        }
-    }
-}
+            fn unit_variant(self) -> Result<()> {
+                Err(Error::custom("unit variant is not supported"))
+            }
 
-impl<'de, 'a, T: Trait> de::Deserializer<'de> for &'a mut Deserializer<T> {
-    type Error = Error;
+            fn newtype_variant_seed<E>(self, seed: E) -> Result<E::Value>
+            where
+                E: DeserializeSeed<'de>,
+            {
+                seed.deserialize(self.0)
+            }
 
-    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_map(MapAccessTop(self))
-    }
+            fn tuple_variant<V>(self, _len: usize, _visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                Err(Error::custom("tuple variant is not supported"))
+            }
 
-    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_seq(SeqAccessTop(self))
+            fn struct_variant<V>(
+                self,
+                fields: &'static [&'static str],
+                visitor: V,
+            ) -> Result<V::Value>
+            where
+                V: Visitor<'de>,
+            {
+                use serde::Deserializer;
+                self.0.deserialize_struct("", fields, visitor)
+            }
+        }
+
+        visitor.visit_enum(EnumAccessSection(self))
     }
 
     fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
@@ -213,112 +1148,160 @@ impl<'de, 'a, T: Trait> de::Deserializer<'de> for &'a mut Deserializer<T> {
 
     forward_to_deserialize_any! {
         bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes
-        byte_buf unit unit_struct newtype_struct tuple tuple_struct
-        map struct identifier ignored_any enum
+        byte_buf unit unit_struct newtype_struct seq map identifier ignored_any
     }
 }
 
-impl<R: io::BufRead> Deserializer<parse::Parser<io::Lines<R>>> {
-    /// Creates an INI deserializer from an `io::BufRead`.
-    pub fn from_bufread(reader: R) -> Self {
-        Deserializer::new(parse::Parser::from_bufread(reader))
-    }
+/// Visits the keys of a section followed by the names of any sections nested below it
+///
+/// The top level of the document is treated as a section without a name, whose keys precede the
+/// first section header.
+struct MapAccessSectionBody<'a, 'de> {
+    config: &'a Config,
+    prefix: Option<&'a str>,
+    /// The fields of the struct being deserialized, if it is one
+    fields: Option<&'static [&'static str]>,
+    /// A key to visit before the body, with the name of the section as its value
+    name_key: Option<&'a str>,
+    entries: &'a mut Entries<'de>,
+    sections: &'a mut [Section<'de>],
 }
 
-impl<R: io::Read> Deserializer<parse::Parser<io::Lines<io::BufReader<R>>>> {
-    /// Creates an INI deserializer from a reader.
-    pub fn from_read(reader: R) -> Self {
-        Deserializer::new(parse::Parser::from_read(reader))
+impl<'de, 'a> MapAccess<'de> for MapAccessSectionBody<'a, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        let (config, prefix, fields) = (self.config, self.prefix, self.fields);
+        if let Some(key) = self.name_key {
+            return seed.deserialize(KeyDeserializer { config, key: Reference::Copied(key) }).map(Some)
+        }
+
+        if let Some(entry) = self.entries.peek() {
+            let key = Reference::new(&entry.key);
+            let key = key.map(|key| config.entry_key(key, "").map(|(key, _)| key)).unwrap_or(key);
+            config.check_field(fields, &key, |expected, suggestion| UnknownItem {
+                section: prefix.map(Into::into),
+                key: Some(key[..].into()),
+                position: entry.position,
+                expected,
+                suggestion,
+            });
+            return seed.deserialize(KeyDeserializer { config, key }).map(Some).map_err(|e| entry.attribute(e))
+        }
+
+        match config.next_section_key(self.sections, self.prefix) {
+            Some((section, key)) => {
+                config.check_field(fields, &key, |expected, suggestion| UnknownItem {
+                    section: Some(config.section_name(&key, prefix)),
+                    key: None,
+                    position: section.position,
+                    expected,
+                    suggestion,
+                });
+                seed.deserialize(KeyDeserializer { config, key }).map(Some).map_err(|e| section.attribute(e))
+            },
+            None => Ok(None),
+        }
     }
-}
 
-impl<'a> Deserializer<parse::Parser<parse::OkIter<str::Lines<'a>>>> {
-    /// Creates an INI deserializer from a `&str`.
-    pub fn from_str(s: &'a str) -> Self {
-        Deserializer::new(parse::Parser::from_str(s))
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        if let (Some(..), Some(name)) = (self.name_key.take(), self.prefix) {
+            return seed.deserialize(KeyDeserializer { config: self.config, key: Reference::Copied(name) })
+        }
+
+        let nested = match self.entries.peek_key() {
+            Some(key) => self.config.nested_key_prefix(key, ""),
+            None => return self.config.deserialize_section(self.sections, self.prefix, false, seed)?
+                .ok_or_else(|| ErrorKind::UnexpectedEof.into()),
+        };
+
+        match nested {
+            Some(prefix) => self.config.deserialize_keys(self.entries, prefix, seed),
+            None => self.config.deserialize_value(self.entries.next_entries()?, self.fields.is_none(), seed),
+        }
     }
 }
 
-pub struct SectionDeserializer<'a, T: 'a>(&'a mut Deserializer<T>);
+/// Deserializes the keys of a section that share a common prefix as a map
+pub struct KeysDeserializer<'a, 'de> {
+    config: &'a Config,
+    /// The prefix, including its trailing separator
+    prefix: &'a str,
+    entries: &'a mut Entries<'de>,
+}
 
-impl<'de, 'a, T: Trait> de::Deserializer<'de> for &'a mut SectionDeserializer<'a, T> {
+impl<'de, 'a> de::Deserializer<'de> for &'a mut KeysDeserializer<'a, 'de> {
     type Error = Error;
 
     fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        struct MapAccessSectionBody<'a, T: Trait + 'a>(&'a mut Deserializer<T>);
-
-        impl<'de, 'a, T: Trait + 'a> MapAccess<'de> for MapAccessSectionBody<'a, T> {
-            type Error = Error;
-
-            fn next_key_seed<K: DeserializeSeed<'de>>(
-                &mut self,
-                seed: K,
-            ) -> Result<Option<K::Value>> {
-                match (self.0).peek_kind()? {
-                    Some(PeekKind::Value) => seed
-                        .deserialize((self.0).peek_key()?.into_deserializer())
-                        .map(Some),
-                    None | Some(PeekKind::Section) => Ok(None),
-                }
-            }
-
-            fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
-                seed.deserialize(&mut ValueDeserializer(self.0))
-            }
-        }
+        visitor.visit_map(MapAccessKeys {
+            config: self.config,
+            prefix: self.prefix,
+            fields: None,
+            entries: self.entries,
+        })
+    }
 
-        (self.0).next_section()?;
-        visitor.visit_map(MapAccessSectionBody(self.0))
+    fn deserialize_struct<V: Visitor<'de>>(self, _name: &'static str, fields: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        visitor.visit_map(MapAccessKeys {
+            config: self.config,
+            prefix: self.prefix,
+            fields: Some(fields),
+            entries: self.entries,
+        })
     }
 
+    /// The first key below the prefix names the variant, as in `backend.File.path = x`.
     fn deserialize_enum<V: Visitor<'de>>(
         self,
         _name: &'static str,
         _variants: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value> {
-        struct EnumAccessSection<'a, T: Trait + 'a>(&'a mut Deserializer<T>);
+        struct EnumAccessKeys<'a, 'de>(&'a mut KeysDeserializer<'a, 'de>);
 
-        impl<'de, 'a, T: Trait + 'a> EnumAccess<'de> for EnumAccessSection<'a, T> {
+        impl<'de, 'a> EnumAccess<'de> for EnumAccessKeys<'a, 'de> {
             type Error = Error;
-            type Variant = VariantAccessSection<'a, T>;
+            type Variant = VariantAccessKeys<'a, 'de>;
 
             fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
             where
                 V: DeserializeSeed<'de>,
             {
-                struct SectionNameDeserializer<'a, T: 'a>(&'a mut Deserializer<T>);
-
-                impl<'de, 'a, T: Trait> de::Deserializer<'de> for &'a mut SectionNameDeserializer<'a, T> {
-                    type Error = Error;
-
-                    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
-                        Err(Error::InvalidState)
-                    }
-
-                    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
-                    where
-                        V: Visitor<'de>,
-                    {
-                        let name = self.0.peek_section()?;
-                        visitor.visit_str(name)
-                    }
-
-                    forward_to_deserialize_any! {
-                        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char bytes
-                        byte_buf str string unit unit_struct newtype_struct seq tuple tuple_struct
-                        map struct ignored_any enum option
-                    }
-                }
-
-                let variant = seed.deserialize(&mut SectionNameDeserializer(self.0))?;
-                Ok((variant, VariantAccessSection(self.0)))
+                let (config, prefix) = (self.0.config, self.0.prefix);
+                let (index, key, nested) = self.0.entries.entries.iter().enumerate()
+                    .filter(|&(_, entry)| !entry.consumed)
+                    .filter_map(|(i, entry)| config.entry_key(&entry.key, prefix).map(|(key, nested)| (i, key.to_owned(), nested)))
+                    .next()
+                    .ok_or(ErrorKind::UnexpectedEof)?;
+
+                let variant = {
+                    let key: de::value::StrDeserializer<Error> = key.as_str().into_deserializer();
+                    seed.deserialize(key)?
+                };
+                let prefix = match (nested, config.key_separator) {
+                    (true, Some(separator)) => Some(format!("{}{}{}", prefix, key, separator)),
+                    _ => None,
+                };
+
+                Ok((variant, VariantAccessKeys {
+                    config,
+                    entries: self.0.entries,
+                    index,
+                    prefix,
+                }))
             }
         }
 
-        struct VariantAccessSection<'a, T: Trait + 'a>(&'a mut Deserializer<T>);
+        struct VariantAccessKeys<'a, 'de> {
+            config: &'a Config,
+            entries: &'a mut Entries<'de>,
+            index: usize,
+            /// The prefix of the keys nested below the variant, if there are any
+            prefix: Option<String>,
+        }
 
-        impl<'de, 'a, T: Trait + 'a> VariantAccess<'de> for VariantAccessSection<'a, T> {
+        impl<'de, 'a> VariantAccess<'de> for VariantAccessKeys<'a, 'de> {
             type Error = Error;
 
             fn unit_variant(self) -> Result<()> {
@@ -329,7 +1312,13 @@ impl<'de, 'a, T: Trait> de::Deserializer<'de> for &'a mut SectionDeserializer<'a
             where
                 E: DeserializeSeed<'de>,
             {
-                seed.deserialize(&mut SectionDeserializer(self.0))
+                match self.prefix {
+                    Some(prefix) => self.config.deserialize_keys(self.entries, prefix, seed),
+                    None => {
+                        self.entries.entries[self.index].consumed = true;
+                        self.config.deserialize_value(&mut self.entries.entries[self.index..], false, seed)
+                    },
+                }
             }
 
             fn tuple_variant<V>(self, _len: usize, _visitor: V) -> Result<V::Value>
@@ -341,18 +1330,25 @@ impl<'de, 'a, T: Trait> de::Deserializer<'de> for &'a mut SectionDeserializer<'a
 
             fn struct_variant<V>(
                 self,
-                _fields: &'static [&'static str],
+                fields: &'static [&'static str],
                 visitor: V,
             ) -> Result<V::Value>
             where
                 V: Visitor<'de>,
             {
                 use serde::Deserializer;
-                SectionDeserializer(self.0).deserialize_any(visitor)
+                match self.prefix {
+                    Some(ref prefix) => KeysDeserializer {
+                        config: self.config,
+                        prefix,
+                        entries: self.entries,
+                    }.deserialize_struct("", fields, visitor),
+                    None => Err(Error::custom("struct variant must contain nested keys")),
+                }
             }
         }
 
-        visitor.visit_enum(EnumAccessSection(self.0))
+        visitor.visit_enum(EnumAccessKeys(self))
     }
 
     fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
@@ -362,88 +1358,188 @@ impl<'de, 'a, T: Trait> de::Deserializer<'de> for &'a mut SectionDeserializer<'a
     forward_to_deserialize_any! {
         bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes
         byte_buf unit unit_struct newtype_struct seq tuple tuple_struct
-        map struct identifier ignored_any
+        map identifier ignored_any
+    }
+}
+
+/// Visits the keys below the prefix of a `KeysDeserializer`
+struct MapAccessKeys<'a, 'de> {
+    config: &'a Config,
+    prefix: &'a str,
+    /// The fields of the struct being deserialized, if it is one
+    fields: Option<&'static [&'static str]>,
+    entries: &'a mut Entries<'de>,
+}
+
+impl<'a, 'de> MapAccessKeys<'a, 'de> {
+    fn next(&self) -> Option<(usize, Reference<'_, 'de>)> {
+        let (config, prefix) = (self.config, self.prefix);
+        self.entries.entries.iter().enumerate()
+            .filter(|&(_, entry)| !entry.consumed)
+            .filter_map(|(i, entry)| Reference::new(&entry.key).map(|key| config.entry_key(key, prefix).map(|(key, _)| key)).map(|key| (i, key)))
+            .next()
+    }
+}
+
+impl<'de, 'a> MapAccess<'de> for MapAccessKeys<'a, 'de> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.next() {
+            Some((i, key)) => {
+                self.config.check_field(self.fields, &key, |expected, suggestion| UnknownItem {
+                    section: None,
+                    key: Some(format!("{}{}", self.prefix, &key[..])),
+                    position: self.entries.entries[i].position,
+                    expected,
+                    suggestion,
+                });
+                seed.deserialize(KeyDeserializer { config: self.config, key }).map(Some)
+                    .map_err(|e| self.entries.entries[i].attribute(e))
+            },
+            None => Ok(None),
+        }
     }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let (i, nested) = match self.next() {
+            Some((i, _)) => (i, self.config.nested_key_prefix(&self.entries.entries[i].key, self.prefix)),
+            None => return Err(ErrorKind::UnexpectedEof.into()),
+        };
+
+        match nested {
+            Some(prefix) => self.config.deserialize_keys(self.entries, prefix, seed),
+            None => {
+                self.entries.entries[i].consumed = true;
+                self.config.deserialize_value(&mut self.entries.entries[i..], self.fields.is_none(), seed)
+            },
+        }
+    }
+}
+
+/// Deserializes the value of a single key
+///
+/// Sequences are formed by collecting every remaining occurrence of the key within its section.
+pub struct ValueDeserializer<'a, 'de> {
+    config: &'a Config,
+    /// The entry being deserialized followed by the remainder of its section
+    entries: &'a mut [Entry<'de>],
+    /// Set for the value of a map rather than of a struct field
+    map: bool,
+    /// The occurrence of the key that was deserialized as a single value
+    chosen: usize,
 }
 
-pub struct ValueDeserializer<'a, T: 'a>(&'a mut Deserializer<T>);
+impl<'a, 'de> ValueDeserializer<'a, 'de> {
+    /// Returns the occurrence of the key to deserialize as a single value, consuming the later
+    /// occurrences that `duplicate_keys` discards.
+    fn single(&mut self) -> &Entry<'de> {
+        let policy = match self.config.duplicate_keys {
+            DuplicateKeys::Collect if self.map => DuplicateKeys::Last,
+            policy => policy,
+        };
+        if let DuplicateKeys::First | DuplicateKeys::Last = policy {
+            let (first, rest) = self.entries.split_first_mut().expect("a value has at least one entry");
+            for (i, entry) in rest.iter_mut().enumerate().filter(|(_, entry)| !entry.consumed && same_key(&entry.key, &first.key)) {
+                entry.consumed = true;
+                if policy == DuplicateKeys::Last {
+                    self.chosen = i + 1;
+                }
+            }
+        }
+
+        &self.entries[self.chosen]
+    }
+
+    fn value(&mut self) -> &str {
+        &self.single().value
+    }
+}
 
-impl<'de, 'a, T: Trait> de::Deserializer<'de> for &'a mut ValueDeserializer<'a, T> {
+impl<'de, 'a, 'b> de::Deserializer<'de> for &'b mut ValueDeserializer<'a, 'de> {
     type Error = Error;
 
     fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        match (self.0).peek_kind()? {
-            Some(PeekKind::Value) => self.deserialize_str(visitor),
-            None | Some(PeekKind::Section) => Err(Error::InvalidState),
+        // a key that is repeated can only be a sequence, even when nothing asked for one
+        if self.entries[1..].iter().any(|entry| !entry.consumed && same_key(&entry.key, &self.entries[0].key)) {
+            return self.deserialize_seq(visitor)
+        }
+
+        match self.config.infer(self.value()) {
+            de::Unexpected::Unsigned(v) => visitor.visit_u64(v),
+            de::Unexpected::Signed(v) => visitor.visit_i64(v),
+            de::Unexpected::Float(v) => visitor.visit_f64(v),
+            de::Unexpected::Bool(v) => visitor.visit_bool(v),
+            _ => self.deserialize_str(visitor),
         }
     }
 
     fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        self.deserialize_any(visitor)
+        visitor.visit_bool(self.config.parse_bool(self.value())?)
     }
 
     fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_i8(FromStr::from_str(&(self.0).next_value()?)?)
+        visitor.visit_i8(parse_str(self.value(), "i8")?)
     }
 
     fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_i16(FromStr::from_str(&(self.0).next_value()?)?)
+        visitor.visit_i16(parse_str(self.value(), "i16")?)
     }
 
     fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_i32(FromStr::from_str(&(self.0).next_value()?)?)
+        visitor.visit_i32(parse_str(self.value(), "i32")?)
     }
 
     fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_i64(FromStr::from_str(&(self.0).next_value()?)?)
+        visitor.visit_i64(parse_str(self.value(), "i64")?)
     }
 
     fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_u8(FromStr::from_str(&(self.0).next_value()?)?)
+        visitor.visit_u8(parse_str(self.value(), "u8")?)
     }
 
     fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_u16(FromStr::from_str(&(self.0).next_value()?)?)
+        visitor.visit_u16(parse_str(self.value(), "u16")?)
     }
 
     fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_u32(FromStr::from_str(&(self.0).next_value()?)?)
+        visitor.visit_u32(parse_str(self.value(), "u32")?)
     }
 
     fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_u64(FromStr::from_str(&(self.0).next_value()?)?)
+        visitor.visit_u64(parse_str(self.value(), "u64")?)
     }
 
     fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_f32(FromStr::from_str(&(self.0).next_value()?)?)
+        visitor.visit_f32(parse_str(self.value(), "f32")?)
     }
 
     fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_f64(FromStr::from_str(&(self.0).next_value()?)?)
+        visitor.visit_f64(parse_str(self.value(), "f64")?)
     }
 
     fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        let value = (self.0).next_value()?;
+        let value = self.value();
         let mut chars = value.chars();
         if let Some(c) = chars.next() {
             if chars.next().is_some() {
                 // >1 char long
-                visitor.visit_str(&value)
+                visitor.visit_str(value)
             } else {
                 visitor.visit_char(c)
             }
         } else {
             // 0 chars long
-            visitor.visit_str(&value)
+            visitor.visit_str(value)
         }
     }
 
     fn deserialize_str<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_str(&(self.0).next_value()?)
+        Reference::new(&self.single().value).visit(visitor)
     }
 
     fn deserialize_string<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        visitor.visit_string((self.0).next_value()?)
+        visitor.visit_string(self.value().into())
     }
 
     fn deserialize_bytes<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
@@ -472,15 +1568,65 @@ impl<'de, 'a, T: Trait> de::Deserializer<'de> for &'a mut ValueDeserializer<'a,
     }
 
     fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
-        self.deserialize_any(visitor)
+        struct SeqAccessValues<'a, 'de> {
+            config: &'a Config,
+            entries: Vec<Entry<'de>>,
+            pos: usize,
+        }
+
+        impl<'de, 'a> SeqAccess<'de> for SeqAccessValues<'a, 'de> {
+            type Error = Error;
+
+            fn next_element_seed<E>(&mut self, seed: E) -> Result<Option<E::Value>>
+            where
+                E: DeserializeSeed<'de>,
+            {
+                let pos = self.pos;
+                if pos < self.entries.len() {
+                    self.pos += 1;
+                    self.config.deserialize_value(&mut self.entries[pos..pos + 1], false, seed).map(Some)
+                } else {
+                    Ok(None)
+                }
+            }
+
+            fn size_hint(&self) -> Option<usize> {
+                Some(self.entries.len() - self.pos)
+            }
+        }
+
+        let (first, rest) = match self.entries.split_first_mut() {
+            Some(entries) => entries,
+            None => return Err(ErrorKind::InvalidState.into()),
+        };
+        let mut entries = vec![first.clone()];
+        for entry in rest.iter_mut().filter(|e| !e.consumed && same_key(&e.key, &first.key)) {
+            entry.consumed = true;
+            entries.push(entry.clone());
+        }
+
+        if let Some(separator) = self.config.list_separator {
+            entries = entries.into_iter().flat_map(|entry| {
+                parse::split_list(&entry.value, separator).into_iter().map(move |value| Entry {
+                    value: value.into(),
+                    .. entry.clone()
+                })
+            }).collect();
+        }
+
+        visitor.visit_seq(SeqAccessValues {
+            config: self.config,
+            entries,
+            pos: 0,
+        })
     }
 
     fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
-        self.deserialize_any(visitor)
+        self.deserialize_seq(visitor)
     }
 
     fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, _len: usize, visitor: V) -> Result<V::Value> {
-        self.deserialize_any(visitor)
+        self.deserialize_seq(visitor)
     }
 
     fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
@@ -492,10 +1638,7 @@ impl<'de, 'a, T: Trait> de::Deserializer<'de> for &'a mut ValueDeserializer<'a,
     }
 
     fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value> {
-        match (self.0).peek_kind()? {
-            Some(PeekKind::Value) => visitor.visit_enum((self.0).next_value()?.into_deserializer()),
-            None | Some(PeekKind::Section) => Err(Error::InvalidState),
-        }
+        visitor.visit_enum(self.value().into_deserializer())
     }
 
     fn deserialize_identifier<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
@@ -507,22 +1650,152 @@ impl<'de, 'a, T: Trait> de::Deserializer<'de> for &'a mut ValueDeserializer<'a,
     }
 }
 
-struct SeqAccessTop<'a, T: Trait + 'a>(&'a mut Deserializer<T>);
+/// Deserializes the name of a key or section, parsing it with the same rules as a value
+pub struct KeyDeserializer<'a, 'de> {
+    config: &'a Config,
+    key: Reference<'a, 'de>,
+}
+
+impl<'de, 'a> de::Deserializer<'de> for KeyDeserializer<'a, 'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.key.visit(visitor)
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_bool(self.config.parse_bool(&self.key)?)
+    }
+
+    fn deserialize_i8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i8(parse_str(&self.key, "i8")?)
+    }
+
+    fn deserialize_i16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i16(parse_str(&self.key, "i16")?)
+    }
+
+    fn deserialize_i32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i32(parse_str(&self.key, "i32")?)
+    }
+
+    fn deserialize_i64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_i64(parse_str(&self.key, "i64")?)
+    }
+
+    fn deserialize_u8<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u8(parse_str(&self.key, "u8")?)
+    }
+
+    fn deserialize_u16<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u16(parse_str(&self.key, "u16")?)
+    }
+
+    fn deserialize_u32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u32(parse_str(&self.key, "u32")?)
+    }
+
+    fn deserialize_u64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_u64(parse_str(&self.key, "u64")?)
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_f32(parse_str(&self.key, "f32")?)
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_f64(parse_str(&self.key, "f64")?)
+    }
+
+    fn deserialize_char<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let mut chars = self.key.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => visitor.visit_char(c),
+            _ => self.key.visit(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(self, _name: &'static str, _variants: &'static [&'static str], visitor: V) -> Result<V::Value> {
+        visitor.visit_enum((&*self.key).into_deserializer())
+    }
+
+    forward_to_deserialize_any! {
+        str string bytes byte_buf unit unit_struct seq tuple tuple_struct
+        map struct identifier ignored_any
+    }
+}
+
+struct SeqAccessTop<'a, 'de> {
+    config: &'a Config,
+    entries: &'a mut Entries<'de>,
+    sections: &'a mut [Section<'de>],
+}
 
-impl<'de, 'a, T: Trait + 'a> SeqAccess<'de> for SeqAccessTop<'a, T> {
+impl<'de, 'a> SeqAccess<'de> for SeqAccessTop<'a, 'de> {
     type Error = Error;
 
     fn next_element_seed<E>(&mut self, seed: E) -> Result<Option<E::Value>>
     where
         E: DeserializeSeed<'de>,
     {
-        pub struct KeyValueDeserializer<'a, T: 'a>(&'a mut Deserializer<T>);
+        pub struct KeyValueDeserializer<'a, 'de> {
+            config: &'a Config,
+            entries: &'a mut [Entry<'de>],
+        }
 
-        impl<'de, 'a, T: Trait> de::Deserializer<'de> for &'a mut KeyValueDeserializer<'a, T> {
+        impl<'de, 'a> de::Deserializer<'de> for &'a mut KeyValueDeserializer<'a, 'de> {
             type Error = Error;
 
             fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
-                Err(Error::custom("expect an enum type"))
+                Err(Error::custom("expect an enum or tuple type"))
+            }
+
+            /// A pair of the key and its value.
+            fn deserialize_tuple<V: Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+                struct SeqAccessKeyValue<'a, 'de> {
+                    config: &'a Config,
+                    entries: Option<&'a mut [Entry<'de>]>,
+                    key: bool,
+                }
+
+                impl<'de, 'a> SeqAccess<'de> for SeqAccessKeyValue<'a, 'de> {
+                    type Error = Error;
+
+                    fn next_element_seed<E>(&mut self, seed: E) -> Result<Option<E::Value>>
+                    where
+                        E: DeserializeSeed<'de>,
+                    {
+                        if self.key {
+                            self.key = false;
+                            let entries = self.entries.as_ref().ok_or(ErrorKind::InvalidState)?;
+                            return seed.deserialize(KeyDeserializer { config: self.config, key: Reference::new(&entries[0].key) }).map(Some)
+                                .map_err(|e| entries[0].attribute(e))
+                        }
+
+                        match self.entries.take() {
+                            Some(entries) => self.config.deserialize_value(entries, false, seed).map(Some),
+                            None => Ok(None),
+                        }
+                    }
+                }
+
+                visitor.visit_seq(SeqAccessKeyValue {
+                    config: self.config,
+                    entries: Some(self.entries),
+                    key: true,
+                })
+            }
+
+            fn deserialize_tuple_struct<V: Visitor<'de>>(self, _name: &'static str, len: usize, visitor: V) -> Result<V::Value> {
+                self.deserialize_tuple(len, visitor)
             }
 
             fn deserialize_enum<V: Visitor<'de>>(
@@ -531,51 +1804,34 @@ impl<'de, 'a, T: Trait + 'a> SeqAccess<'de> for SeqAccessTop<'a, T> {
                 _variants: &'static [&'static str],
                 visitor: V,
             ) -> Result<V::Value> {
-                struct EnumAccessKeyValue<'a, T: Trait + 'a>(&'a mut Deserializer<T>);
+                struct EnumAccessKeyValue<'a, 'de> {
+                    config: &'a Config,
+                    entries: &'a mut [Entry<'de>],
+                }
 
-                impl<'de, 'a, T: Trait + 'a> EnumAccess<'de> for EnumAccessKeyValue<'a, T> {
+                impl<'de, 'a> EnumAccess<'de> for EnumAccessKeyValue<'a, 'de> {
                     type Error = Error;
-                    type Variant = VariantAccessKeyValue<'a, T>;
+                    type Variant = VariantAccessKeyValue<'a, 'de>;
 
                     fn variant_seed<V>(self, seed: V) -> Result<(V::Value, Self::Variant)>
                     where
                         V: DeserializeSeed<'de>,
                     {
-                        struct KeyDeserializer<'a, T: 'a>(&'a mut Deserializer<T>);
-
-                        impl<'de, 'a, T: Trait> de::Deserializer<'de> for &'a mut KeyDeserializer<'a, T> {
-                            type Error = Error;
-
-                            fn deserialize_any<V: Visitor<'de>>(
-                                self,
-                                _visitor: V,
-                            ) -> Result<V::Value> {
-                                Err(Error::InvalidState)
-                            }
-
-                            fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
-                            where
-                                V: Visitor<'de>,
-                            {
-                                let name = self.0.peek_key()?;
-                                visitor.visit_str(name)
-                            }
-
-                            forward_to_deserialize_any! {
-                                bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char bytes
-                                byte_buf str string unit unit_struct newtype_struct seq tuple tuple_struct
-                                map struct ignored_any enum option
-                            }
-                        }
-
-                        let variant = seed.deserialize(&mut KeyDeserializer(self.0))?;
-                        Ok((variant, VariantAccessKeyValue(self.0)))
+                        let key: de::value::StrDeserializer<Error> = self.entries[0].key[..].into_deserializer();
+                        let variant = seed.deserialize(key)?;
+                        Ok((variant, VariantAccessKeyValue {
+                            config: self.config,
+                            entries: self.entries,
+                        }))
                     }
                 }
 
-                struct VariantAccessKeyValue<'a, T: Trait + 'a>(&'a mut Deserializer<T>);
+                struct VariantAccessKeyValue<'a, 'de> {
+                    config: &'a Config,
+                    entries: &'a mut [Entry<'de>],
+                }
 
-                impl<'de, 'a, T: Trait + 'a> VariantAccess<'de> for VariantAccessKeyValue<'a, T> {
+                impl<'de, 'a> VariantAccess<'de> for VariantAccessKeyValue<'a, 'de> {
                     type Error = Error;
 
                     fn unit_variant(self) -> Result<()> {
@@ -586,7 +1842,7 @@ impl<'de, 'a, T: Trait + 'a> SeqAccess<'de> for SeqAccessTop<'a, T> {
                     where
                         E: DeserializeSeed<'de>,
                     {
-                        seed.deserialize(&mut ValueDeserializer(self.0))
+                        self.config.deserialize_value(self.entries, false, seed)
                     }
 
                     fn tuple_variant<V>(self, _len: usize, _visitor: V) -> Result<V::Value>
@@ -608,59 +1864,63 @@ impl<'de, 'a, T: Trait + 'a> SeqAccess<'de> for SeqAccessTop<'a, T> {
                     }
                 }
 
-                visitor.visit_enum(EnumAccessKeyValue(self.0))
+                visitor.visit_enum(EnumAccessKeyValue {
+                    config: self.config,
+                    entries: self.entries,
+                })
             }
 
             forward_to_deserialize_any! {
                 bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes
-                byte_buf unit unit_struct newtype_struct seq tuple tuple_struct
-                map struct identifier ignored_any option
+                byte_buf unit unit_struct newtype_struct seq map struct identifier
+                ignored_any option
             }
         }
 
-        match (self.0).peek_kind()? {
-            Some(PeekKind::Value) => seed.deserialize(&mut KeyValueDeserializer(self.0)),
-            Some(PeekKind::Section) => seed.deserialize(&mut SectionDeserializer(self.0)),
-            None => return Ok(None),
-        }
-        .map(Some)
-    }
-}
-
-struct MapAccessTop<'a, T: Trait + 'a>(&'a mut Deserializer<T>);
-
-impl<'de, 'a, T: Trait + 'a> MapAccess<'de> for MapAccessTop<'a, T> {
-    type Error = Error;
-
-    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
-        match (self.0).peek_kind()? {
-            Some(PeekKind::Value) => seed.deserialize((self.0).peek_key()?.into_deserializer()),
-            Some(PeekKind::Section) => {
-                seed.deserialize((self.0).peek_section()?.into_deserializer())
-            }
-            None => return Ok(None),
+        if self.entries.peek_key().is_some() {
+            // every top-level key is an element of its own, even when repeated
+            return seed.deserialize(&mut KeyValueDeserializer {
+                config: self.config,
+                entries: &mut self.entries.next_entries()?[..1],
+            }).map(Some)
         }
-        .map(Some)
-    }
 
-    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
-        match (self.0).peek_kind()? {
-            Some(PeekKind::Value) => seed.deserialize(&mut ValueDeserializer(self.0)),
-            Some(PeekKind::Section) => seed.deserialize(&mut SectionDeserializer(self.0)),
-            None => Err(Error::UnexpectedEof),
-        }
+        self.config.deserialize_section(self.sections, None, true, seed)
     }
 }
 
 /// Deserialize an instance of type `T` from a string of INI text.
-pub fn from_str<T: DeserializeOwned>(s: &str) -> Result<T> {
-    let mut de = Deserializer::new(parse::Parser::from_str(s.as_ref()));
+///
+/// Unlike the other functions, `T` can borrow strings from `s`, as with a `&'de str` field, so
+/// long as they are not split as a list.
+pub fn from_str<'de, T: Deserialize<'de>>(s: &'de str) -> Result<T> {
+    let mut de = Deserializer::new(parse::Parser::from_str(s));
     let value = Deserialize::deserialize(&mut de)?;
 
     de.assert_eof()?;
     Ok(value)
 }
 
+/// Deserializes the values of a key with the default options, as they would be within a section.
+///
+/// Each occurrence of the key is given as its value and position, of which there must be at least
+/// one. Errors are attributed to the first.
+pub(crate) fn from_values<'v, T, I>(key: &str, values: I) -> Result<T>
+where
+    T: DeserializeOwned,
+    I: IntoIterator<Item=(&'v str, Option<Position>)>,
+{
+    let mut entries: Vec<_> = values.into_iter().map(|(value, position)| Entry {
+        key: key.into(),
+        value: value.into(),
+        position,
+        index: 0,
+        consumed: false,
+    }).collect();
+
+    Config::default().deserialize_value(&mut entries, false, PhantomData)
+}
+
 /// Deserialize an instance of type `T` from a buffered IO stream of INI.
 pub fn from_bufread<R: io::BufRead, T: DeserializeOwned>(reader: R) -> Result<T> {
     let mut de = Deserializer::new(parse::Parser::from_bufread(reader));
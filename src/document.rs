@@ -0,0 +1,453 @@
+use std::ops::Range;
+use std::str::FromStr;
+use std::{fmt, result};
+use parse::{Parser, Item, OkIter, SyntaxError};
+use write::LineEnding;
+use error::{Error, ErrorKind, Result};
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+enum Kind {
+    /// A comment or empty line
+    Other,
+    Section(String),
+    /// The byte range of the trimmed value within the line
+    Value(String, Range<usize>),
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Line {
+    text: String,
+    /// `None` for a final line that is not terminated
+    ending: Option<LineEnding>,
+    kind: Kind,
+}
+
+impl Line {
+    fn new(text: String, kind: Kind) -> Self {
+        Line {
+            text,
+            ending: None,
+            kind,
+        }
+    }
+
+    fn section(name: &str) -> Self {
+        Line::new(format!("[{}]", name), Kind::Section(name.into()))
+    }
+
+    fn value(key: &str, value: &str) -> Self {
+        let text = format!("{}={}", key, value);
+        let range = text.len() - value.len()..text.len();
+        Line::new(text, Kind::Value(key.into(), range))
+    }
+}
+
+/// Returns why a section header would not read back as the same name, if it would not.
+fn section_error(name: &str) -> Option<SyntaxError> {
+    if name.contains(&['\r', '\n'][..]) {
+        Some(SyntaxError::LineBreak)
+    } else if name.contains(']') {
+        Some(SyntaxError::SectionName)
+    } else {
+        None
+    }
+}
+
+/// Returns why a line would not read back as the same key and value, if it would not.
+fn value_error(key: &str, value: &str) -> Option<SyntaxError> {
+    if key.contains(&['\r', '\n'][..]) || value.contains(&['\r', '\n'][..]) {
+        Some(SyntaxError::LineBreak)
+    } else if key.contains('=') || key.starts_with(&['[', ';', '#'][..]) {
+        Some(SyntaxError::KeyName)
+    } else {
+        None
+    }
+}
+
+/// Checks that a section header can be written as a line that reads back as the same name.
+fn check_section(name: &str) -> Result<()> {
+    match section_error(name) {
+        Some(e) => Err(Error::from(ErrorKind::Syntax(e)).in_section(name)),
+        None => Ok(()),
+    }
+}
+
+/// Checks that a key and its value can be written as a line that reads back as the same pair.
+fn check_value(section: Option<&str>, key: &str, value: &str) -> Result<()> {
+    let error = match value_error(key, value) {
+        Some(e) => Error::from(ErrorKind::Syntax(e)).in_key(key, Some(value)),
+        None => return section.map(check_section).unwrap_or(Ok(())),
+    };
+    Err(match section {
+        Some(section) => error.in_section(section),
+        None => error,
+    })
+}
+
+/// An INI document that preserves its formatting, for editing a file in place
+///
+/// Every line is kept as written, including comments, blank lines, whitespace around `=` and line
+/// endings, so writing the document back out with `Display` reproduces its input exactly apart
+/// from the edits made to it. Changed values keep the surrounding text of their line, and new
+/// lines are written as `key=value` using the line ending of the input.
+///
+/// Top-level keys, those before the first section header, are addressed with a section of
+/// `None`. A section whose header is repeated is treated as one section spread over each of them.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct Document {
+    lines: Vec<Line>,
+    line_ending: LineEnding,
+}
+
+impl Document {
+    /// Creates an empty document, which writes new lines with `LineEnding::default()`.
+    pub fn new() -> Self {
+        Document::default()
+    }
+
+    /// Returns the names of the sections in the order they appear, once per header.
+    pub fn sections(&self) -> impl Iterator<Item=&str> {
+        self.lines.iter().filter_map(|line| match line.kind {
+            Kind::Section(ref name) => Some(&name[..]),
+            _ => None,
+        })
+    }
+
+    pub fn contains_section(&self, name: &str) -> bool {
+        self.sections().any(|s| s == name)
+    }
+
+    /// Appends an empty section to the document, unless one with the same name exists.
+    ///
+    /// Returns whether the section was added, or an error if the name contains `]` or a line break.
+    pub fn insert_section(&mut self, name: &str) -> Result<bool> {
+        check_section(name)?;
+        Ok(self.append_section(name))
+    }
+
+    /// Removes every header of a section along with the lines that follow it, including any
+    /// comments, up to the next section header.
+    ///
+    /// Returns whether the section was present.
+    pub fn remove_section(&mut self, name: &str) -> bool {
+        let blocks = self.blocks(Some(name));
+        for block in blocks.iter().rev() {
+            self.remove_lines(block.start - 1..block.end);
+        }
+        !blocks.is_empty()
+    }
+
+    /// Returns the keys of a section in the order they appear, once per occurrence.
+    pub fn keys(&self, section: Option<&str>) -> impl Iterator<Item=&str> {
+        self.blocks(section).into_iter()
+            .flat_map(move |block| self.lines[block].iter())
+            .filter_map(|line| match line.kind {
+                Kind::Value(ref key, _) => Some(&key[..]),
+                _ => None,
+            })
+    }
+
+    /// Returns the value of the first occurrence of a key.
+    pub fn get(&self, section: Option<&str>, key: &str) -> Option<&str> {
+        self.find(section, key).first().map(|&i| self.value(i))
+    }
+
+    /// Sets the value of a key, adding it to the end of the section if it is not present.
+    ///
+    /// The first occurrence of the key is changed in place and any repetitions of it are removed.
+    /// A missing section is appended to the document. Returns the previous value, if any, or an
+    /// error if the line would not read back as the same key and value.
+    pub fn set(&mut self, section: Option<&str>, key: &str, value: &str) -> Result<Option<String>> {
+        check_value(section, key, value)?;
+        let found = self.find(section, key);
+        let first = match found.split_first() {
+            Some((&first, rest)) => {
+                for &i in rest.iter().rev() {
+                    self.remove_lines(i..i + 1);
+                }
+                first
+            },
+            None => {
+                self.append(section, key, value);
+                return Ok(None)
+            },
+        };
+
+        Ok(Some(self.replace_value(first, value)))
+    }
+
+    /// Adds a key to the end of a section, after any existing occurrences of it.
+    ///
+    /// Repeating a key this way is how sequences are represented. A missing section is appended
+    /// to the document. Fails as `set` does.
+    pub fn insert(&mut self, section: Option<&str>, key: &str, value: &str) -> Result<()> {
+        check_value(section, key, value)?;
+        self.append(section, key, value);
+        Ok(())
+    }
+
+    /// Removes the line of each occurrence of a key, under every header of its section, and returns
+    /// the value of the first. The comments around those lines are kept.
+    pub fn remove(&mut self, section: Option<&str>, key: &str) -> Option<String> {
+        let previous = self.get(section, key).map(Into::into);
+        for i in self.find(section, key).into_iter().rev() {
+            self.remove_lines(i..i + 1);
+        }
+        previous
+    }
+
+    /// Merges serialized sections and values into the document, as described by
+    /// `Serializer::update_document`.
+    ///
+    /// Fails without changing the document if an item would not read back as written.
+    pub(crate) fn update<'a, I: IntoIterator<Item=Item<'a>>>(&mut self, items: I, remove_missing: bool) -> result::Result<(), SyntaxError> {
+        let mut sections = vec![(None, Vec::<(String, Vec<String>)>::new())];
+        let mut current = 0;
+        for item in items {
+            let error = match item {
+                Item::Section { ref name } => section_error(name),
+                Item::Value { ref key, ref value } => value_error(key, value),
+                Item::Comment { .. } | Item::Empty => None,
+            };
+            if let Some(e) = error {
+                return Err(e)
+            }
+
+            match item {
+                Item::Section { name } => {
+                    current = sections.iter().position(|s| s.0.as_deref() == Some(&name[..])).unwrap_or_else(|| {
+                        sections.push((Some(name.into_owned()), Vec::new()));
+                        sections.len() - 1
+                    });
+                },
+                Item::Value { key, value } => {
+                    let keys = &mut sections[current].1;
+                    match keys.iter_mut().find(|k| k.0 == key) {
+                        Some(k) => k.1.push(value.into_owned()),
+                        None => keys.push((key.into_owned(), vec![value.into_owned()])),
+                    }
+                },
+                Item::Comment { .. } | Item::Empty => (),
+            }
+        }
+
+        if remove_missing {
+            let missing = self.sections()
+                .filter(|&name| !sections.iter().any(|s| s.0.as_deref() == Some(name)))
+                .map(String::from)
+                .collect::<Vec<_>>();
+            for name in missing {
+                self.remove_section(&name);
+            }
+        }
+
+        for (section, keys) in sections {
+            let section = section.as_deref();
+            if let Some(section) = section {
+                self.append_section(section);
+            }
+            for (key, values) in &keys {
+                self.set_values(section, key, values);
+            }
+            if remove_missing {
+                let missing = self.keys(section)
+                    .filter(|&key| !keys.iter().any(|k| k.0 == key))
+                    .map(String::from)
+                    .collect::<Vec<_>>();
+                for key in missing {
+                    self.remove(section, &key);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Changes the occurrences of a key to hold `values` in order, leaving those that already do
+    /// untouched.
+    fn set_values(&mut self, section: Option<&str>, key: &str, values: &[String]) {
+        let found = self.find(section, key);
+        for (&i, value) in found.iter().zip(values) {
+            if self.value(i) != value {
+                self.replace_value(i, value);
+            }
+        }
+        for &i in found.iter().skip(values.len()).rev() {
+            self.remove_lines(i..i + 1);
+        }
+        if values.len() > found.len() {
+            match found.last() {
+                // further elements follow the last occurrence
+                Some(&last) => for (i, value) in values[found.len()..].iter().enumerate() {
+                    self.insert_line(last + 1 + i, Line::value(key, value));
+                },
+                None => for value in values {
+                    self.append(section, key, value);
+                },
+            }
+        }
+    }
+
+    fn append_section(&mut self, name: &str) -> bool {
+        if self.contains_section(name) {
+            return false
+        }
+
+        let end = self.lines.len();
+        self.insert_line(end, Line::section(name));
+        true
+    }
+
+    /// Adds a key to the end of a section, as `insert` does without checking it.
+    fn append(&mut self, section: Option<&str>, key: &str, value: &str) {
+        let blocks = self.blocks(section);
+        let index = match blocks.last() {
+            // after the last key of the section, leaving any comments that lead into the next one
+            Some(block) => self.lines[block.clone()].iter()
+                .rposition(|line| matches!(line.kind, Kind::Value(..)))
+                .map(|i| block.start + i + 1)
+                .unwrap_or(if section.is_some() { block.start } else { block.end }),
+            None => {
+                let end = self.lines.len();
+                self.insert_line(end, Line::section(section.expect("top-level keys always have a block")));
+                end + 1
+            },
+        };
+        self.insert_line(index, Line::value(key, value));
+    }
+
+    fn value(&self, index: usize) -> &str {
+        match self.lines[index].kind {
+            Kind::Value(_, ref range) => &self.lines[index].text[range.clone()],
+            _ => unreachable!(),
+        }
+    }
+
+    /// Changes the value of a line in place, returning the previous one.
+    fn replace_value(&mut self, index: usize, value: &str) -> String {
+        let line = &mut self.lines[index];
+        match line.kind {
+            Kind::Value(_, ref mut range) => {
+                let previous = line.text[range.clone()].to_owned();
+                line.text.replace_range(range.clone(), value);
+                range.end = range.start + value.len();
+                previous
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    /// Returns the ranges of lines following each header of a section.
+    fn blocks(&self, section: Option<&str>) -> Vec<Range<usize>> {
+        let mut blocks = Vec::new();
+        let mut current = match section {
+            None => Some(0),
+            Some(..) => None,
+        };
+        for (i, line) in self.lines.iter().enumerate() {
+            if let Kind::Section(ref name) = line.kind {
+                if let Some(start) = current.take() {
+                    blocks.push(start..i);
+                }
+                if section == Some(&name[..]) {
+                    current = Some(i + 1);
+                }
+            }
+        }
+        if let Some(start) = current {
+            blocks.push(start..self.lines.len());
+        }
+        blocks
+    }
+
+    /// Returns the indices of the lines holding a key.
+    fn find(&self, section: Option<&str>, key: &str) -> Vec<usize> {
+        self.blocks(section).into_iter()
+            .flatten()
+            .filter(|&i| match self.lines[i].kind {
+                Kind::Value(ref k, _) => k == key,
+                _ => false,
+            })
+            .collect()
+    }
+
+    fn remove_lines(&mut self, range: Range<usize>) {
+        let unterminated = range.end == self.lines.len() && self.lines.last().is_some_and(|l| l.ending.is_none());
+        self.lines.drain(range);
+        if unterminated {
+            if let Some(last) = self.lines.last_mut() {
+                last.ending = None;
+            }
+        }
+    }
+
+    fn insert_line(&mut self, index: usize, mut line: Line) {
+        // a new last line is only terminated if the old one was
+        let terminated = index < self.lines.len() || self.lines.last().is_none_or(|l| l.ending.is_some());
+        if index > 0 {
+            // a line added after an unterminated last line needs to end it first
+            let previous = &mut self.lines[index - 1];
+            if previous.ending.is_none() {
+                previous.ending = Some(self.line_ending);
+            }
+        }
+        if terminated {
+            line.ending = Some(self.line_ending);
+        }
+        self.lines.insert(index, line);
+    }
+}
+
+impl FromStr for Document {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut lines = Vec::new();
+        let mut line_ending = None;
+        for line in s.split_inclusive('\n') {
+            let (text, ending) = if let Some(text) = line.strip_suffix("\r\n") {
+                (text, Some(LineEnding::CrLf))
+            } else if let Some(text) = line.strip_suffix('\n') {
+                (text, Some(LineEnding::Linefeed))
+            } else {
+                (line, None)
+            };
+            line_ending = line_ending.or(ending);
+            lines.push((text, ending));
+        }
+
+        let parser = Parser::new(OkIter(lines.iter().map(|&(text, _)| text)));
+        let lines = parser.zip(&lines).map(|(item, &(text, ending))| {
+            let kind = match item?.1 {
+                Item::Section { name } => Kind::Section(name.into_owned()),
+                Item::Value { key, value } => {
+                    let start = text.find('=').expect("values contain '='") + 1;
+                    let start = text.len() - text[start..].trim_start().len();
+                    Kind::Value(key.into_owned(), start..start + value.len())
+                },
+                Item::Comment { .. } | Item::Empty => Kind::Other,
+            };
+            Ok(Line {
+                text: text.into(),
+                ending,
+                kind,
+            })
+        }).collect::<Result<_>>()?;
+
+        Ok(Document {
+            lines,
+            line_ending: line_ending.unwrap_or_default(),
+        })
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in &self.lines {
+            f.write_str(&line.text)?;
+            if let Some(ending) = line.ending {
+                write!(f, "{}", ending)?;
+            }
+        }
+        Ok(())
+    }
+}
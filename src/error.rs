@@ -1,40 +1,331 @@
 use serde;
 use std::fmt::{self, Display};
+use std::{error, io};
+use parse::{self, Position, SyntaxError};
+use de::UnknownItem;
+use void::Void;
 
-#[derive(Debug, Clone)]
-pub enum Error {
-    Custom(String)
+/// The cause of an `Error`
+#[derive(Debug)]
+pub enum ErrorKind {
+    /// Reading the input failed
+    Io(io::Error),
+
+    /// The input is not valid INI
+    Syntax(SyntaxError),
+
+    /// A value could not be converted to the type being deserialized
+    ///
+    /// Both fields are descriptions, as in `expected: "u32", found: "string \"abc\""`.
+    InvalidValue {
+        expected: String,
+        found: String,
+    },
+
+    /// A section or key required by the type being deserialized is not present
+    MissingField(&'static str),
+
+    /// A key looked up with `Ini` is not present
+    MissingKey,
+
+    /// A section or key is not recognized by the type being deserialized
+    UnknownField {
+        /// The name of the field, or the full name of the key or section if found by
+        /// `Deserializer::deny_unknown`
+        field: String,
+        /// The fields that the type would have accepted
+        expected: &'static [&'static str],
+        /// The most similar of the `expected` fields, if `Deserializer::deny_unknown` found one
+        suggestion: Option<&'static str>,
+    },
+
+    /// A key appears more than once where only one value was expected
+    DuplicateKey {
+        key: String,
+        /// The position of the first occurrence, if it is known
+        first: Option<Position>,
+    },
+
+    /// The input continues after the value was fully deserialized
+    TrailingData,
+
+    /// Deserialization error
+    ///
+    /// Passed through error message from the type being deserialized.
+    Custom(String),
+
+    /// Internal consistency error
+    ///
+    /// Encountering this is probably misuse of the deserialization API or a bug in serde-ini.
+    UnexpectedEof,
+
+    /// Internal consistency error
+    ///
+    /// Encountering this is probably misuse of the deserialization API or a bug in serde-ini.
+    InvalidState,
 }
 
-impl Display for Error {
+impl Display for ErrorKind {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Error::Custom(msg) => write!(f, "{}", msg),
+            ErrorKind::Io(e) => write!(f, "I/O error: {}", e),
+            ErrorKind::Syntax(e) => write!(f, "INI syntax error: {}", e),
+            ErrorKind::InvalidValue { expected, found } => write!(f, "invalid value: {}, expected {}", found, expected),
+            ErrorKind::MissingField(field) => write!(f, "missing field `{}`", field),
+            ErrorKind::MissingKey => write!(f, "missing key"),
+            ErrorKind::UnknownField { field, suggestion: Some(suggestion), .. } => write!(f, "unknown field `{}`, did you mean `{}`?", field, suggestion),
+            ErrorKind::UnknownField { field, expected, suggestion: None } => {
+                write!(f, "unknown field `{}`", field)?;
+                for (i, expected) in expected.iter().enumerate() {
+                    write!(f, "{}`{}`", if i == 0 { ", expected one of " } else { ", " }, expected)?;
+                }
+                Ok(())
+            },
+            ErrorKind::DuplicateKey { key, first: Some(first) } => write!(f, "duplicate key `{}` (first at {})", key, first),
+            ErrorKind::DuplicateKey { key, first: None } => write!(f, "duplicate key `{}`", key),
+            ErrorKind::TrailingData => write!(f, "trailing data after the deserialized value"),
+            ErrorKind::Custom(msg) => write!(f, "{}", msg),
+            ErrorKind::UnexpectedEof => write!(f, "internal consistency error: unexpected EOF"),
+            ErrorKind::InvalidState => write!(f, "internal consistency error"),
         }
     }
 }
 
-impl From<super::de::Error> for Error {
-    fn from(e: super::de::Error) -> Self {
-        Error::Custom(e.to_string())
+/// Deserialization error, along with where in the input it occurred if that is known
+///
+/// The location is given both as a position in the input, and as the section, key and raw value
+/// that were being deserialized. The message includes the reason a value failed to parse, which is
+/// also available as the `source` of the error.
+#[derive(Debug)]
+pub struct Error(Box<ErrorImpl>);
+
+#[derive(Debug)]
+struct ErrorImpl {
+    kind: ErrorKind,
+    source: Option<Box<dyn error::Error + Send + Sync>>,
+    position: Option<Position>,
+    file: Option<String>,
+    section: Option<String>,
+    key: Option<String>,
+    value: Option<String>,
+    /// The buffered key or section that the error can be avoided by skipping
+    item: Option<usize>,
+}
+
+impl Error {
+    pub fn kind(&self) -> &ErrorKind {
+        &self.0.kind
+    }
+
+    /// Returns the position of the item that caused the error.
+    pub fn position(&self) -> Option<Position> {
+        self.0.position
+    }
+
+    pub fn line(&self) -> Option<usize> {
+        self.0.position.map(|p| p.line)
+    }
+
+    pub fn column(&self) -> Option<usize> {
+        self.0.position.map(|p| p.column)
+    }
+
+    /// Returns the name of the file being deserialized, if one was provided to the `Deserializer`.
+    pub fn file(&self) -> Option<&str> {
+        self.0.file.as_ref().map(|s| &s[..])
+    }
+
+    /// Returns the full name of the section that caused the error, unless it was a top-level key.
+    pub fn section(&self) -> Option<&str> {
+        self.0.section.as_ref().map(|s| &s[..])
+    }
+
+    /// Returns the full name of the key that caused the error.
+    ///
+    /// For a map of dotted keys, this is their shared prefix, as in `db.pool`.
+    pub fn key(&self) -> Option<&str> {
+        self.0.key.as_ref().map(|s| &s[..])
+    }
+
+    /// Returns the raw value that caused the error.
+    pub fn value(&self) -> Option<&str> {
+        self.0.value.as_ref().map(|s| &s[..])
+    }
+
+    /// Creates an `InvalidValue` error caused by `source`.
+    pub(crate) fn invalid_value_from<E: error::Error + Send + Sync + 'static>(source: E, value: &str, expected: &str) -> Self {
+        let mut error = Error::from(ErrorKind::InvalidValue {
+            expected: expected.into(),
+            found: serde::de::Unexpected::Str(value).to_string(),
+        });
+        error.0.source = Some(Box::new(source));
+        error
+    }
+
+    /// Attributes the error to an item, unless it is already attributed to one nested within it.
+    pub(crate) fn at(mut self, position: Option<Position>) -> Self {
+        if self.0.position.is_none() {
+            self.0.position = position;
+        }
+        self
+    }
+
+    /// Attributes the error to a key, unless it is already attributed to one nested within it.
+    pub(crate) fn in_key(mut self, key: &str, value: Option<&str>) -> Self {
+        if self.0.key.is_none() {
+            self.0.key = Some(key.into());
+            self.0.value = value.map(Into::into);
+        }
+        self
+    }
+
+    pub(crate) fn in_section(mut self, name: &str) -> Self {
+        if self.0.section.is_none() {
+            self.0.section = Some(name.into());
+        }
+        self
+    }
+
+    /// Attributes the error to the name or value of a buffered item, numbered in document order.
+    pub(crate) fn in_item(mut self, index: usize) -> Self {
+        if self.0.item.is_none() {
+            self.0.item = Some(index);
+        }
+        self
+    }
+
+    pub(crate) fn item(&self) -> Option<usize> {
+        self.0.item
+    }
+
+    pub(crate) fn in_file(mut self, file: Option<&str>) -> Self {
+        if self.0.file.is_none() {
+            self.0.file = file.map(Into::into);
+        }
+        self
     }
 }
 
-impl ::std::error::Error for Error {
-    fn description(&self) -> &str {
-        "INI serialization error"
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Error(Box::new(ErrorImpl {
+            kind,
+            source: None,
+            position: None,
+            file: None,
+            section: None,
+            key: None,
+            value: None,
+            item: None,
+        }))
     }
 }
 
-impl serde::ser::Error for Error {
-    fn custom<T: Display>(msg: T) -> Self {
-        Error::Custom(msg.to_string())
+impl From<UnknownItem> for Error {
+    fn from(item: UnknownItem) -> Self {
+        let mut e = Error::from(ErrorKind::UnknownField {
+            field: item.key.clone().or_else(|| item.section.clone()).unwrap_or_default(),
+            expected: item.expected,
+            suggestion: item.suggestion,
+        }).at(item.position);
+        e.0.section = item.section;
+        e.0.key = item.key;
+        e
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        ErrorKind::Io(e).into()
+    }
+}
+
+impl From<Void> for Error {
+    fn from(e: Void) -> Self {
+        match e { }
+    }
+}
+
+impl<E> From<parse::Error<E>> for Error where Error: From<E> {
+    fn from(e: parse::Error<E>) -> Self {
+        match e {
+            parse::Error::Inner(e) => e.into(),
+            parse::Error::Syntax(e, position) => <Error as From<ErrorKind>>::from(ErrorKind::Syntax(e)).at(Some(position)),
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let e = &self.0;
+        match (&e.section, &e.key) {
+            (None, None) => (),
+            (Some(section), None) => write!(f, "in [{}]: ", section)?,
+            (Some(section), Some(key)) => write!(f, "in [{}] {}", section, key)?,
+            (None, Some(key)) => write!(f, "in {}", key)?,
+        }
+        match (&e.key, &e.value) {
+            (Some(..), Some(value)) => write!(f, " = {:?}: ", value)?,
+            (Some(..), None) => write!(f, ": ")?,
+            _ => (),
+        }
+
+        write!(f, "{}", e.kind)?;
+        if let Some(ref source) = e.source {
+            write!(f, " ({})", source)?;
+        }
+
+        match (&e.file, e.position) {
+            (Some(file), Some(position)) => write!(f, " at {} of {}", position, file),
+            (Some(file), None) => write!(f, " in {}", file),
+            (None, Some(position)) => write!(f, " at {}", position),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self.0.kind {
+            ErrorKind::Io(ref e) => Some(e),
+            _ => self.0.source.as_ref().map(|e| &**e as &(dyn error::Error + 'static)),
+        }
     }
 }
 
 impl serde::de::Error for Error {
     fn custom<T: Display>(msg: T) -> Self {
-        Error::Custom(msg.to_string())
+        ErrorKind::Custom(msg.to_string()).into()
+    }
+
+    fn invalid_type(unexp: serde::de::Unexpected, exp: &dyn serde::de::Expected) -> Self {
+        ErrorKind::InvalidValue {
+            expected: exp.to_string(),
+            found: unexp.to_string(),
+        }.into()
+    }
+
+    fn invalid_value(unexp: serde::de::Unexpected, exp: &dyn serde::de::Expected) -> Self {
+        Self::invalid_type(unexp, exp)
+    }
+
+    fn unknown_field(field: &str, expected: &'static [&'static str]) -> Self {
+        ErrorKind::UnknownField {
+            field: field.into(),
+            expected,
+            suggestion: None,
+        }.into()
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        ErrorKind::MissingField(field).into()
+    }
+
+    fn duplicate_field(field: &'static str) -> Self {
+        ErrorKind::DuplicateKey {
+            key: field.into(),
+            first: None,
+        }.into()
     }
 }
 
@@ -0,0 +1,239 @@
+use std::str::FromStr;
+use std::{io, result};
+use serde::de::DeserializeOwned;
+use parse::{self, Parser, Item, Position};
+use write::Writer;
+use de;
+use error::{Error, ErrorKind, Result};
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Property {
+    key: String,
+    value: String,
+    position: Option<Position>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+struct Section {
+    name: Option<String>,
+    position: Option<Position>,
+    properties: Vec<Property>,
+}
+
+/// Sections and keys read from an INI file, for looking up values by name
+///
+/// This sits between iterating over a `Parser` and deserializing a type, much like Python's
+/// `configparser`. Values are parsed as the `Deserializer` would parse them with its default
+/// options, and errors refer to the line of the key, or of the section header if the key is
+/// missing.
+///
+/// Top-level keys, those before the first section header, are found in the section `None`, apart
+/// from any section with an empty `[]` header. Repeated section headers are combined into one
+/// section.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Ini {
+    /// The top-level keys followed by each section
+    sections: Vec<Section>,
+}
+
+impl Default for Ini {
+    fn default() -> Self {
+        Ini {
+            sections: vec![Section {
+                name: None,
+                position: None,
+                properties: Vec::new(),
+            }],
+        }
+    }
+}
+
+impl Ini {
+    pub fn new() -> Self {
+        Ini::default()
+    }
+
+    /// Reads every item from `parser`, stopping at the first error.
+    pub fn from_parser<'a, E, S: parse::Line<Item=Item<'a>>, T: Iterator<Item=result::Result<S, E>>>(parser: Parser<T>) -> Result<Self>
+    where
+        Error: From<parse::Error<E>>,
+    {
+        let mut ini = Ini::new();
+        let mut current = 0;
+        for item in parser {
+            match item? {
+                (position, Item::Section { name }) => {
+                    current = match ini.section_index(Some(&name)) {
+                        Some(i) => i,
+                        None => {
+                            ini.sections.push(Section {
+                                name: Some(name.into_owned()),
+                                position: Some(position),
+                                properties: Vec::new(),
+                            });
+                            ini.sections.len() - 1
+                        },
+                    };
+                },
+                (position, Item::Value { key, value }) => ini.sections[current].properties.push(Property {
+                    key: key.into_owned(),
+                    value: value.into_owned(),
+                    position: Some(position),
+                }),
+                (_, Item::Comment { .. }) | (_, Item::Empty) => (),
+            }
+        }
+        Ok(ini)
+    }
+
+    pub fn from_bufread<R: io::BufRead>(reader: R) -> Result<Self> {
+        Ini::from_parser(Parser::from_bufread(reader))
+    }
+
+    pub fn from_read<R: io::Read>(reader: R) -> Result<Self> {
+        Ini::from_parser(Parser::from_read(reader))
+    }
+
+    /// Returns the names of the sections in the order they first appear, not including the
+    /// top-level section.
+    pub fn sections(&self) -> impl Iterator<Item=&str> {
+        self.sections.iter().filter_map(|section| section.name.as_ref().map(|name| &name[..]))
+    }
+
+    pub fn has_section(&self, section: &str) -> bool {
+        self.section_index(Some(section)).is_some()
+    }
+
+    /// Returns the keys of a section in the order they first appear, once each.
+    pub fn keys(&self, section: Option<&str>) -> impl Iterator<Item=&str> {
+        let properties = match self.section_index(section) {
+            Some(i) => &self.sections[i].properties[..],
+            None => &[],
+        };
+        properties.iter().enumerate()
+            .filter(move |&(i, property)| !properties[..i].iter().any(|p| p.key == property.key))
+            .map(|(_, property)| &property.key[..])
+    }
+
+    /// Returns the raw value of the first occurrence of a key.
+    pub fn get(&self, section: Option<&str>, key: &str) -> Result<&str> {
+        let properties = self.properties(section, key)?;
+        Ok(&properties[0].value)
+    }
+
+    /// Parses the value of a key as a `T`, or a sequence if it is repeated.
+    pub fn get_as<T: DeserializeOwned>(&self, section: Option<&str>, key: &str) -> Result<T> {
+        let properties = self.properties(section, key)?;
+        de::from_values(key, properties.iter().map(|p| (&p.value[..], p.position)))
+            .map_err(|e| in_section(e, section))
+    }
+
+    /// Parses the value of a key as a `T`, or returns `default` if the key is not present.
+    ///
+    /// A value that is present but invalid is still an error.
+    pub fn get_or<T: DeserializeOwned>(&self, section: Option<&str>, key: &str, default: T) -> Result<T> {
+        match self.properties(section, key) {
+            Ok(..) => self.get_as(section, key),
+            Err(..) => Ok(default),
+        }
+    }
+
+    pub fn get_int(&self, section: Option<&str>, key: &str) -> Result<i64> {
+        self.get_as(section, key)
+    }
+
+    pub fn get_float(&self, section: Option<&str>, key: &str) -> Result<f64> {
+        self.get_as(section, key)
+    }
+
+    /// Parses a value as `true`, `yes`, `on` or `1`, or `false`, `no`, `off` or `0`, ignoring case.
+    pub fn get_bool(&self, section: Option<&str>, key: &str) -> Result<bool> {
+        self.get_as(section, key)
+    }
+
+    /// Sets the value of a key, replacing every occurrence of it and adding the section if it is
+    /// not present.
+    pub fn set<V: Into<String>>(&mut self, section: Option<&str>, key: &str, value: V) {
+        let i = match self.section_index(section) {
+            Some(i) => i,
+            None => {
+                self.sections.push(Section {
+                    name: section.map(Into::into),
+                    position: None,
+                    properties: Vec::new(),
+                });
+                self.sections.len() - 1
+            },
+        };
+
+        let properties = &mut self.sections[i].properties;
+        let property = Property {
+            key: key.into(),
+            value: value.into(),
+            position: None,
+        };
+        match properties.iter().position(|p| p.key == key) {
+            Some(first) => {
+                properties[first] = property;
+                let mut index = 0;
+                properties.retain(|p| {
+                    index += 1;
+                    index <= first + 1 || p.key != key
+                });
+            },
+            None => properties.push(property),
+        }
+    }
+
+    /// Writes every section and key to `writer`, without the comments of the input.
+    pub fn write<W: io::Write>(&self, writer: &mut Writer<W>) -> io::Result<()> {
+        for section in &self.sections {
+            if let Some(ref name) = section.name {
+                writer.write(&Item::Section { name: (&name[..]).into() })?;
+            }
+            for property in &section.properties {
+                writer.write(&Item::Value {
+                    key: (&property.key[..]).into(),
+                    value: (&property.value[..]).into(),
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    fn section_index(&self, section: Option<&str>) -> Option<usize> {
+        self.sections.iter().position(|s| s.name.as_ref().map(|name| &name[..]) == section)
+    }
+
+    /// Returns every occurrence of a key, or an error if there are none.
+    fn properties(&self, section: Option<&str>, key: &str) -> Result<Vec<&Property>> {
+        let (properties, position) = match self.section_index(section) {
+            Some(i) => {
+                let s = &self.sections[i];
+                (s.properties.iter().filter(|p| p.key == key).collect::<Vec<_>>(), s.position)
+            },
+            None => (Vec::new(), None),
+        };
+        if properties.is_empty() {
+            let e = Error::from(ErrorKind::MissingKey).at(position).in_key(key, None);
+            return Err(in_section(e, section))
+        }
+        Ok(properties)
+    }
+}
+
+/// Attributes an error to a section, unless it is in the top-level section.
+fn in_section(e: Error, section: Option<&str>) -> Error {
+    match section {
+        Some(section) => e.in_section(section),
+        None => e,
+    }
+}
+
+impl FromStr for Ini {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Ini::from_parser(Parser::from_str(s))
+    }
+}
@@ -8,13 +8,22 @@ extern crate void;
 #[macro_use]
 extern crate serde;
 
+#[macro_use]
+mod macros;
+
 pub mod de;
+pub mod document;
 pub mod error;
+pub mod ini;
 pub mod parse;
 pub mod ser;
+pub mod value;
 pub mod write;
 
-pub use de::{Deserializer, from_str, from_bufread, from_read};
-pub use ser::{Serializer, to_string, to_vec, to_writer};
-pub use parse::{Parser, Item};
+pub use de::{Deserializer, DuplicateKeys, from_str, from_bufread, from_read};
+pub use ser::{Serializer, to_string, to_vec, to_writer, to_document};
+pub use parse::{Parser, Item, Position};
 pub use write::{Writer, LineEnding};
+pub use document::Document;
+pub use ini::Ini;
+pub use value::{Value, to_value, from_value};
@@ -0,0 +1,46 @@
+/// Builds a `Value` from top-level keys and sections written out in place
+///
+/// Keys are written as `"key" => value` separated by commas, and sections as `[name] { .. }`
+/// holding keys of their own, as in `ini!{ "name" => "app", [server] { "port" => 80 } }`. A section
+/// name that is not an identifier can be given as a string, as in `["server.tls"]`.
+///
+/// Values are any expressions implementing `Serialize`, written as `to_string` would write them,
+/// so a sequence becomes a repeated key. Panics if a value cannot be written as a single key.
+#[macro_export]
+macro_rules! ini {
+    (@document $doc:ident;) => {};
+    (@document $doc:ident; , $($rest:tt)*) => {
+        $crate::ini!(@document $doc; $($rest)*)
+    };
+    (@document $doc:ident; [$name:ident] { $($body:tt)* } $($rest:tt)*) => {
+        $crate::ini!(@section $doc stringify!($name); { $($body)* } $($rest)*)
+    };
+    (@document $doc:ident; [$name:literal] { $($body:tt)* } $($rest:tt)*) => {
+        $crate::ini!(@section $doc $name; { $($body)* } $($rest)*)
+    };
+    (@document $doc:ident; $key:expr => $value:expr $(, $($rest:tt)*)?) => {
+        $crate::value::insert_serialized(&mut $doc.global, $key, &$value);
+        $crate::ini!(@document $doc; $($($rest)*)?)
+    };
+
+    (@section $doc:ident $name:expr; { $($body:tt)* } $($rest:tt)*) => {
+        $doc.sections.push(($name.into(), Vec::new()));
+        $crate::ini!(@entries $doc; $($body)*);
+        $crate::ini!(@document $doc; $($rest)*)
+    };
+
+    (@entries $doc:ident;) => {};
+    (@entries $doc:ident; $key:expr => $value:expr $(, $($rest:tt)*)?) => {
+        if let Some(section) = $doc.sections.last_mut() {
+            $crate::value::insert_serialized(&mut section.1, $key, &$value);
+        }
+        $crate::ini!(@entries $doc; $($($rest)*)?)
+    };
+
+    ($($tt:tt)*) => {{
+        #[allow(unused_mut)]
+        let mut doc = $crate::Value::new();
+        $crate::ini!(@document doc; $($tt)*);
+        doc
+    }};
+}
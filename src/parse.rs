@@ -1,27 +1,76 @@
+use std::borrow::Cow;
 use std::{io, fmt, error, str};
-use result::prelude::*;
 use void::Void;
 
+/// A line of INI, whose text borrows from the input when it is parsed from a `&str`
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
-pub enum Item {
+pub enum Item<'a> {
     Empty,
     Section {
-        name: String
+        name: Cow<'a, str>,
     },
     Value {
-        key: String,
-        value: String,
+        key: Cow<'a, str>,
+        value: Cow<'a, str>,
     },
     Comment {
-        text: String
+        /// The character that starts the comment, `;` or `#`
+        ///
+        /// `None` leaves the choice to the `Writer`, which uses its `comment_prefix`.
+        prefix: Option<char>,
+        /// The text following the prefix, including any leading whitespace
+        text: Cow<'a, str>,
     },
 }
 
+impl<'a> Item<'a> {
+    /// Copies any borrowed text, so that the item no longer refers to its input.
+    pub fn into_owned(self) -> Item<'static> {
+        match self {
+            Item::Empty => Item::Empty,
+            Item::Section { name } => Item::Section { name: name.into_owned().into() },
+            Item::Value { key, value } => Item::Value { key: key.into_owned().into(), value: value.into_owned().into() },
+            Item::Comment { prefix, text } => Item::Comment { prefix, text: text.into_owned().into() },
+        }
+    }
+}
+
+/// A line of input for `Parser`
+///
+/// Items parsed from a `&str` borrow their text from it, while those parsed from a `String`, as
+/// read from an `io::BufRead`, own their text.
+pub trait Line: AsRef<str> {
+    /// The `Item` parsed from the line
+    type Item;
+
+    fn parse(self) -> Result<Self::Item, SyntaxError>;
+}
+
+impl<'a> Line for &'a str {
+    type Item = Item<'a>;
+
+    fn parse(self) -> Result<Item<'a>, SyntaxError> {
+        parse_line(self)
+    }
+}
+
+impl Line for String {
+    type Item = Item<'static>;
+
+    fn parse(self) -> Result<Item<'static>, SyntaxError> {
+        parse_line(&self).map(Item::into_owned)
+    }
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
 pub enum SyntaxError {
     SectionNotClosed,
     SectionName,
     MissingEquals,
+    /// A key contains `=` or starts with `[` or a comment prefix, so it would not read back
+    KeyName,
+    /// A key, value or section name contains a line break
+    LineBreak,
 }
 
 impl fmt::Display for SyntaxError {
@@ -30,14 +79,41 @@ impl fmt::Display for SyntaxError {
             SyntaxError::SectionNotClosed => write!(f, "section missing ']'"),
             SyntaxError::SectionName => write!(f, "section name contains ']'"),
             SyntaxError::MissingEquals => write!(f, "variable assignment missing '='"),
+            SyntaxError::KeyName => write!(f, "key contains '=' or starts with '[', ';' or '#'"),
+            SyntaxError::LineBreak => write!(f, "line break within a single line"),
         }
     }
 }
 
+/// The location of an item within its input
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Default, Debug)]
+pub struct Position {
+    /// The line number, starting at 1
+    pub line: usize,
+    /// The byte offset of the item within its line, starting at 1
+    pub column: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
 #[derive(Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
 pub enum Error<E> {
     Inner(E),
-    Syntax(SyntaxError),
+    Syntax(SyntaxError, Position),
+}
+
+impl<E> Error<E> {
+    /// Returns the position of the line that failed to parse, if the error is a syntax error.
+    pub fn position(&self) -> Option<Position> {
+        match *self {
+            Error::Inner(..) => None,
+            Error::Syntax(_, position) => Some(position),
+        }
+    }
 }
 
 impl<E> From<E> for Error<E> {
@@ -50,20 +126,13 @@ impl<E: fmt::Display> fmt::Display for Error<E> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
             Error::Inner(ref e) => fmt::Display::fmt(e, f),
-            Error::Syntax(s) => write!(f, "INI syntax error: {}", s),
+            Error::Syntax(s, position) => write!(f, "INI syntax error: {} at {}", s, position),
         }
     }
 }
 
-impl<E: error::Error> error::Error for Error<E> {
-    fn description(&self) -> &str {
-        match *self {
-            Error::Inner(ref e) => e.description(),
-            Error::Syntax(..) => "INI syntax error",
-        }
-    }
-
-    fn cause(&self) -> Option<&error::Error> {
+impl<E: error::Error + 'static> error::Error for Error<E> {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match *self {
             Error::Inner(ref e) => Some(e),
             _ => None,
@@ -71,14 +140,20 @@ impl<E: error::Error> error::Error for Error<E> {
     }
 }
 
+/// Parses lines of input into an `Item` each, along with the `Position` of that item
+///
+/// Every line is parsed on its own, so iteration can continue past a syntax error to find any
+/// others in the input. Errors reading the input should be treated as final.
 pub struct Parser<T> {
     input: T,
+    line: usize,
 }
 
 impl<T> Parser<T> {
     pub fn new(input: T) -> Self {
         Parser {
-            input: input,
+            input,
+            line: 0,
         }
     }
 
@@ -88,6 +163,7 @@ impl<T> Parser<T> {
 }
 
 impl<'a> Parser<OkIter<str::Lines<'a>>> {
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &'a str) -> Self {
         Self::new(OkIter(s.lines()))
     }
@@ -105,57 +181,114 @@ impl<R: io::Read> Parser<io::Lines<io::BufReader<R>>> {
     }
 }
 
-impl<T> Parser<T> {
-    fn parse_next<E, S: AsRef<str>>(line: Option<S>) -> Result<Option<Item>, Error<E>> {
-        let line = match line {
-            Some(line) => line,
-            None => return Ok(None),
-        };
-        let line = line.as_ref();
-
-        if line.starts_with('[') {
-            if line.ends_with(']') {
-                let line = &line[1..line.len() - 1];
-                if line.contains(']') {
-                    Err(Error::Syntax(SyntaxError::SectionName))
-                } else {
-                    Ok(Some(Item::Section {
-                        name: line.into(),
-                    }))
-                }
+fn parse_line(line: &str) -> Result<Item<'_>, SyntaxError> {
+    if line.starts_with('[') {
+        if line.ends_with(']') {
+            let line = &line[1..line.len() - 1];
+            if line.contains(']') {
+                Err(SyntaxError::SectionName)
             } else {
-                Err(Error::Syntax(SyntaxError::SectionNotClosed))
+                Ok(Item::Section {
+                    name: line.into(),
+                })
             }
-        } else if line.starts_with(';') || line.starts_with('#') {
-            Ok(Some(Item::Comment {
-                text: line.into(),
-            }))
         } else {
-            let mut line = line.splitn(2, '=');
-            if let Some(key) = line.next() {
-                if let Some(value) = line.next() {
-                    Ok(Some(Item::Value {
-                        key: key.trim().into(),
-                        value: value.trim().into(),
-                    }))
-                } else if key.is_empty() {
-                    Ok(Some(Item::Empty))
-                } else {
-                    Err(Error::Syntax(SyntaxError::MissingEquals))
-                }
+            Err(SyntaxError::SectionNotClosed)
+        }
+    } else if line.starts_with(';') || line.starts_with('#') {
+        Ok(Item::Comment {
+            prefix: line.chars().next(),
+            text: line[1..].into(),
+        })
+    } else {
+        let mut line = line.splitn(2, '=');
+        if let Some(key) = line.next() {
+            if let Some(value) = line.next() {
+                Ok(Item::Value {
+                    key: key.trim().into(),
+                    value: value.trim().into(),
+                })
+            } else if key.is_empty() {
+                Ok(Item::Empty)
             } else {
-                unreachable!()
+                Err(SyntaxError::MissingEquals)
             }
+        } else {
+            unreachable!()
         }
     }
 }
 
-impl<E, S: AsRef<str>, T: Iterator<Item=Result<S, E>>> Iterator for Parser<T> {
-    type Item = Result<Item, Error<E>>;
+impl<E, S: Line, T: Iterator<Item=Result<S, E>>> Iterator for Parser<T> {
+    type Item = Result<(Position, S::Item), Error<E>>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.input.next_invert().map_err(Error::Inner).and_then(|l| Self::parse_next(l)).invert()
+        let line = match self.input.next()? {
+            Ok(line) => line,
+            Err(e) => return Some(Err(Error::Inner(e))),
+        };
+        self.line += 1;
+
+        let text = line.as_ref();
+        let position = Position {
+            line: self.line,
+            column: text.len() - text.trim_start().len() + 1,
+        };
+        Some(line.parse()
+            .map(|item| (position, item))
+            .map_err(|e| Error::Syntax(e, position)))
+    }
+}
+
+/// Splits a delimited list value such as `a, b, c` into its elements.
+///
+/// Elements are trimmed of surrounding whitespace, and may be wrapped in double quotes to preserve
+/// whitespace or contain the separator. A backslash escapes a following separator, quote, or
+/// backslash; any other backslash is kept as-is. A trailing separator (as in `a;b;c;`) does not
+/// produce an empty final element.
+pub fn split_list(value: &str, separator: char) -> Vec<String> {
+    fn unquote(s: &str, separator: char) -> String {
+        let mut out = String::with_capacity(s.len());
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => match chars.peek() {
+                    Some(&next) if next == separator || next == '"' || next == '\\' => {
+                        out.push(next);
+                        chars.next();
+                    },
+                    _ => out.push(c),
+                },
+                '"' => (),
+                c => out.push(c),
+            }
+        }
+        out
     }
+
+    let mut items = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            quoted = !quoted;
+        } else if c == separator && !quoted {
+            items.push(unquote(value[start..i].trim(), separator));
+            start = i + c.len_utf8();
+        }
+    }
+
+    let last = value[start..].trim();
+    if !last.is_empty() {
+        items.push(unquote(last, separator));
+    }
+
+    items
 }
 
 pub struct OkIter<I>(pub I);
@@ -1,21 +1,27 @@
 use std::io::{self, Write};
-use std::{result, fmt};
+use std::borrow::Cow;
+use std::{error, result, fmt};
 use serde::ser::{self, Serialize, Impossible};
-use write::Writer;
-use parse::Item;
+use write::{Writer, LineEnding, join_list};
+use parse::{Item, SyntaxError};
+use de::{DuplicateKeys, same_key};
+use document::Document;
 
 #[derive(Copy, Clone, Debug)]
 pub enum UnsupportedType {
-    Bool,
     Bytes,
     None,
     Unit,
     Seq,
     Map,
+    NewtypeVariant,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub enum Error {
+    /// Writing the output failed
+    Io(io::Error),
+
     /// Serialization error
     ///
     /// Passed through error message from the type being serialized.
@@ -26,15 +32,25 @@ pub enum Error {
     /// INI values can only be strings, or numeric values supported by `FromStr`.
     UnsupportedType(UnsupportedType),
 
-    /// INI section and key names must be a string
+    /// INI section and key names must be a string, or a number, `bool` or unit variant written as one
     NonStringKey,
 
-    /// An entire INI file can only be serialized from a map or struct type
+    /// An entire INI file can only be serialized from a map or struct type, or a sequence of enums
+    /// or named sections
     TopLevelMap,
 
+    /// A map or struct in a top-level sequence must start with the key set by `section_name_key`
+    SectionName,
+
     /// Top-level values without a section cannot be serialized after a section has been written
     OrphanValue,
 
+    /// A key, value or section name written into a `Document` would not read back as written
+    Syntax(SyntaxError),
+
+    /// Two keys of a map are the same when case is ignored, and `DuplicateKeys::Error` is set
+    DuplicateKey(String),
+
     /// Serializer consistency error
     ///
     /// This error indicates that the `SerializeMap` API was misused.
@@ -43,7 +59,7 @@ pub enum Error {
 
 impl From<io::Error> for Error {
     fn from(e: io::Error) -> Self {
-        Error::Custom(e.to_string())
+        Error::Io(e)
     }
 }
 
@@ -56,19 +72,26 @@ impl From<UnsupportedType> for Error {
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
+            Error::Io(e) => write!(f, "I/O error: {}", e),
             Error::Custom(msg) => write!(f, "{}", msg),
             Error::UnsupportedType(ty) => write!(f, "{:?} cannot be serialized into INI", ty),
-            Error::NonStringKey => write!(f, "INI map keys must be a string type"),
+            Error::NonStringKey => write!(f, "INI map keys must be a string, number, bool or unit variant"),
             Error::OrphanValue => write!(f, "top-level INI values must be serialized before any map sections"),
+            Error::Syntax(e) => write!(f, "INI syntax error: {}", e),
+            Error::DuplicateKey(key) => write!(f, "duplicate key `{}` when case is ignored", key),
             Error::MapKeyMissing => write!(f, "serializer consistency error: attempted to serialize map value without key"),
-            Error::TopLevelMap => write!(f, "INI can only represent a map or struct type"),
+            Error::TopLevelMap => write!(f, "INI can only represent a map or struct type, or a sequence of enums or named sections"),
+            Error::SectionName => write!(f, "a map or struct in a top-level INI sequence must start with the key that names its section"),
         }
     }
 }
 
-impl ::std::error::Error for Error {
-    fn description(&self) -> &str {
-        "INI serialization error"
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io(e) => Some(e),
+            _ => None,
+        }
     }
 }
 
@@ -80,65 +103,239 @@ impl ser::Error for Error {
 
 pub type Result<T> = result::Result<T, Error>;
 
+#[derive(Debug, Clone)]
+struct Config {
+    true_value: String,
+    false_value: String,
+    list_separator: Option<char>,
+    section_separator: Option<char>,
+    key_separator: Option<char>,
+    duplicate_keys: DuplicateKeys,
+    section_name_key: Option<String>,
+    remove_missing: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            true_value: "true".into(),
+            false_value: "false".into(),
+            list_separator: None,
+            section_separator: None,
+            key_separator: None,
+            duplicate_keys: Default::default(),
+            section_name_key: None,
+            remove_missing: false,
+        }
+    }
+}
+
 pub struct Serializer<W> {
     writer: Writer<W>,
+    config: Config,
+    /// Collects the items written instead of the writer, for `serialize_items`
+    items: Option<Vec<Item<'static>>>,
 }
 
 impl<W> Serializer<W> {
     pub fn new(writer: Writer<W>) -> Self {
         Serializer {
-            writer: writer,
+            writer,
+            config: Default::default(),
+            items: None,
+        }
+    }
+
+    /// Sets the spellings written for `true` and `false` values.
+    ///
+    /// Defaults to `true` and `false`.
+    pub fn bool_values<S: Into<String>>(mut self, true_value: S, false_value: S) -> Self {
+        self.config.true_value = true_value.into();
+        self.config.false_value = false_value.into();
+        self
+    }
+
+    /// Writes sequences as a single value joined by `separator` instead of repeating the key.
+    ///
+    /// Elements are quoted when necessary, as described by `write::join_list`.
+    pub fn list_separator(mut self, separator: char) -> Self {
+        self.config.list_separator = Some(separator);
+        self
+    }
+
+    /// Writes structs and maps nested within a section as sections of their own, naming them by
+    /// joining their keys with `separator`, as in `[server.tls]`.
+    pub fn section_separator(mut self, separator: char) -> Self {
+        self.config.section_separator = Some(separator);
+        self
+    }
+
+    /// Flattens structs and maps nested within a section into keys joined by `separator`, as in
+    /// `db.pool.max=10`.
+    ///
+    /// Takes precedence over `section_separator` within a section.
+    pub fn key_separator(mut self, separator: char) -> Self {
+        self.config.key_separator = Some(separator);
+        self
+    }
+
+    /// Writes each map or struct in a top-level sequence as a section named by its value for `key`,
+    /// which must be its first key.
+    ///
+    /// This is the inverse of `Deserializer::section_name_key`. Sections can be serialized from
+    /// `(name, body)` tuples without it.
+    pub fn section_name_key<S: Into<String>>(mut self, key: S) -> Self {
+        self.config.section_name_key = Some(key.into());
+        self
+    }
+
+    /// Sets how map keys that are the same when case is ignored, such as `Port` and `port`, are
+    /// written.
+    ///
+    /// By default both are written. The deserializer also ignores case, and reads them back as a
+    /// sequence, as the last value of a map, or as an error for a single struct field.
+    /// `DuplicateKeys::Last` holds back each map until it is complete.
+    pub fn duplicate_keys(mut self, policy: DuplicateKeys) -> Self {
+        self.config.duplicate_keys = policy;
+        self
+    }
+
+    /// Removes the keys and sections of a document passed to `update_document` that are not
+    /// written for the value, such as those for fields it no longer has.
+    ///
+    /// Note that fields skipped when they are `None` are then removed as well.
+    pub fn remove_missing(mut self, remove: bool) -> Self {
+        self.config.remove_missing = remove;
+        self
+    }
+
+    /// Serializes `value` into an existing document, keeping the comments and formatting of
+    /// everything that it does not change.
+    ///
+    /// Values are compared as they would be written, and only those that differ are replaced in
+    /// place. Keys and sections that the document does not have yet are added to the end of their
+    /// section and of the document. The writer of this serializer is not used.
+    ///
+    /// Fails with `Error::Syntax`, leaving the document unchanged, if a key, value or section name
+    /// would not read back as written.
+    pub fn update_document<T: Serialize + ?Sized>(&self, document: &mut Document, value: &T) -> Result<()> {
+        let items = self.serialize_items(value)?;
+        document.update(items, self.config.remove_missing).map_err(Error::Syntax)
+    }
+
+    /// Serializes `value` with the options of this serializer, returning the items written.
+    pub(crate) fn serialize_items<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<Item<'static>>> {
+        let mut ser = Serializer {
+            writer: Writer::new(io::sink(), LineEnding::Linefeed),
+            config: self.config.clone(),
+            items: Some(Vec::new()),
+        };
+        value.serialize(&mut ser)?;
+        Ok(ser.items.unwrap_or_default())
+    }
+
+    /// Returns where to write items, along with the options to write them with.
+    fn output(&mut self) -> (Output<'_, W>, &Config) {
+        let out = match self.items {
+            Some(ref mut items) => Output::Items(items),
+            None => Output::Writer(&mut self.writer),
+        };
+        (out, &self.config)
+    }
+}
+
+/// Destination for serialized items
+///
+/// Items are kept in memory for sections nested within another section, which are deferred until
+/// the values of their parent have all been written, and for `Serializer::serialize_items`.
+enum Output<'a, W: 'a> {
+    Writer(&'a mut Writer<W>),
+    Items(&'a mut Vec<Item<'static>>),
+}
+
+impl<'a, W: Write> Output<'a, W> {
+    fn write(&mut self, item: Item<'static>) -> Result<()> {
+        match *self {
+            Output::Writer(ref mut writer) => writer.write(&item).map_err(Into::into),
+            Output::Items(ref mut items) => {
+                items.push(item);
+                Ok(())
+            },
+        }
+    }
+
+    fn reborrow(&mut self) -> Output<'_, W> {
+        match *self {
+            Output::Writer(ref mut writer) => Output::Writer(writer),
+            Output::Items(ref mut items) => Output::Items(items),
         }
     }
 }
 
 struct ValueSerializer<'a, 'k, W: 'a> {
-    writer: &'a mut Writer<W>,
+    out: Output<'a, W>,
+    /// Nested sections are written here until the current section is complete
+    deferred: &'a mut Vec<Item<'static>>,
+    config: &'a Config,
+    /// Name of the section containing the value
+    section: Option<&'k str>,
     key: &'k str,
     top_level: bool,
     allow_values: &'a mut bool,
+    /// Set for an element of a sequence, which cannot itself be a sequence
+    element: bool,
+    /// Collects the value instead of writing it, for elements of a delimited list
+    list: Option<&'a mut Vec<String>>,
 }
 
 pub struct MapSerializer<'a, W: 'a> {
-    writer: &'a mut Writer<W>,
+    out: Output<'a, W>,
+    deferred: Vec<Item<'static>>,
+    config: &'a Config,
+    section: Option<String>,
+    /// Prepended to each key when flattening a nested map into dotted keys
+    prefix: String,
     key: Option<String>,
     top_level: bool,
     allow_values: bool,
+    /// The map keys written so far, along with their values and nested sections if they are held
+    /// back to be replaced by a later duplicate
+    keys: Vec<(String, Vec<Item<'static>>, Vec<Item<'static>>)>,
+    /// Set when the value of the current key is to be ignored as a duplicate
+    skip: bool,
 }
 
 impl<'a, 'k, W: Write> ValueSerializer<'a, 'k, W> {
     fn serialize_string(&mut self, s: String) -> Result<()> {
-        if !self.top_level || *self.allow_values {
-            self.writer.write(&Item::Value {
-                key: self.key.into(),
-                value: s,
-            }).map_err(Into::into)
+        if let Some(ref mut list) = self.list {
+            list.push(s);
+            Ok(())
+        } else if !self.top_level || *self.allow_values {
+            self.out.write(Item::Value {
+                key: self.key.to_owned().into(),
+                value: s.into(),
+            })
         } else {
             Err(Error::OrphanValue)
         }
     }
-
-    fn serialize_section(&mut self) -> Result<()> {
-        self.writer.write(&Item::Section {
-            name: self.key.into(),
-        }).map_err(Into::into)
-    }
 }
 
 impl<'a, 'k, W: Write + 'a> ser::Serializer for ValueSerializer<'a, 'k, W> {
     type Ok = ();
     type Error = Error;
 
-    type SerializeSeq = Impossible<Self::Ok, Self::Error>;
-    type SerializeTuple = Impossible<Self::Ok, Self::Error>;
-    type SerializeTupleStruct = Impossible<Self::Ok, Self::Error>;
+    type SerializeSeq = SeqSerializer<'a, 'k, W>;
+    type SerializeTuple = SeqSerializer<'a, 'k, W>;
+    type SerializeTupleStruct = SeqSerializer<'a, 'k, W>;
     type SerializeTupleVariant = Impossible<Self::Ok, Self::Error>;
     type SerializeMap = MapSerializer<'a, W>;
     type SerializeStruct = MapSerializer<'a, W>;
-    type SerializeStructVariant = Impossible<Self::Ok, Self::Error>;
+    type SerializeStructVariant = MapSerializer<'a, W>;
 
-    fn serialize_bool(self, _v: bool) -> Result<()> {
-        Err(UnsupportedType::Bool.into())
+    fn serialize_bool(mut self, v: bool) -> Result<()> {
+        let value = if v { &self.config.true_value } else { &self.config.false_value };
+        self.serialize_string(value.clone())
     }
 
     fn serialize_i8(mut self, v: i8) -> Result<()> {
@@ -217,12 +414,41 @@ impl<'a, 'k, W: Write + 'a> ser::Serializer for ValueSerializer<'a, 'k, W> {
         value.serialize(self)
     }
 
-    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _value: &T) -> Result<()> {
-        Err(UnsupportedType::Unit.into())
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _name: &'static str, _variant_index: u32, variant: &'static str, value: &T) -> Result<()> {
+        let separator = match (self.top_level, self.config.key_separator, &self.list) {
+            (false, Some(separator), &None) => separator,
+            _ => return Err(UnsupportedType::NewtypeVariant.into()),
+        };
+
+        let key = format!("{}{}{}", self.key, separator, variant);
+        value.serialize(ValueSerializer {
+            out: self.out,
+            deferred: self.deferred,
+            config: self.config,
+            section: self.section,
+            key: &key,
+            top_level: false,
+            allow_values: self.allow_values,
+            element: false,
+            list: None,
+        })
     }
 
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
-        Err(UnsupportedType::Seq.into())
+        if self.element {
+            return Err(UnsupportedType::Seq.into())
+        }
+
+        Ok(SeqSerializer {
+            out: self.out,
+            deferred: self.deferred,
+            config: self.config,
+            section: self.section,
+            key: self.key,
+            top_level: self.top_level,
+            allow_values: self.allow_values,
+            list: self.config.list_separator.map(|_| Vec::new()),
+        })
     }
 
     fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
@@ -237,35 +463,172 @@ impl<'a, 'k, W: Write + 'a> ser::Serializer for ValueSerializer<'a, 'k, W> {
         Err(UnsupportedType::Seq.into())
     }
 
-    fn serialize_map(mut self, _len: Option<usize>) -> Result<Self::SerializeMap> {
-        if self.top_level {
-            *self.allow_values = false;
-            self.serialize_section().map(move |_| MapSerializer {
-                writer: self.writer,
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        if self.list.is_some() {
+            return Err(UnsupportedType::Map.into())
+        }
+
+        if let (false, Some(separator)) = (self.top_level, self.config.key_separator) {
+            return Ok(MapSerializer {
+                out: self.out,
+                deferred: Vec::new(),
+                config: self.config,
+                section: self.section.map(Into::into),
+                prefix: format!("{}{}", self.key, separator),
                 key: None,
                 top_level: false,
                 allow_values: false,
+                keys: Vec::new(),
+                skip: false,
             })
-        } else {
-            Err(UnsupportedType::Map.into())
         }
+
+        let (mut out, name) = match (self.top_level, self.section, self.config.section_separator) {
+            (true, _, _) => {
+                *self.allow_values = false;
+                (self.out, self.key.to_owned())
+            },
+            (false, Some(section), Some(separator)) =>
+                (Output::Items(self.deferred), format!("{}{}{}", section, separator, self.key)),
+            (false, _, _) => return Err(UnsupportedType::Map.into()),
+        };
+
+        out.write(Item::Section {
+            name: name.clone().into(),
+        })?;
+
+        Ok(MapSerializer {
+            out,
+            deferred: Vec::new(),
+            config: self.config,
+            section: Some(name),
+            prefix: String::new(),
+            key: None,
+            top_level: false,
+            allow_values: false,
+            keys: Vec::new(),
+            skip: false,
+        })
     }
 
     fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
         self.serialize_map(Some(len))
     }
 
-    fn serialize_struct_variant(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeStructVariant> {
-        Err(UnsupportedType::Map.into())
+    fn serialize_struct_variant(self, _name: &'static str, _variant_index: u32, variant: &'static str, _len: usize) -> Result<Self::SerializeStructVariant> {
+        let separator = match (self.top_level, self.config.key_separator, &self.list) {
+            (false, Some(separator), &None) => separator,
+            _ => return Err(UnsupportedType::Map.into()),
+        };
+
+        Ok(MapSerializer {
+            out: self.out,
+            deferred: Vec::new(),
+            config: self.config,
+            section: self.section.map(Into::into),
+            prefix: format!("{}{}{}{}", self.key, separator, variant, separator),
+            key: None,
+            top_level: false,
+            allow_values: false,
+            keys: Vec::new(),
+            skip: false,
+        })
+    }
+}
+
+/// Serializes each element of a sequence as a repetition of the same key, or joins them into a
+/// single delimited value if a list separator is configured.
+///
+/// An empty sequence repeats the key no times, so it is read back as a missing field unless the
+/// field is `#[serde(default)]`.
+struct SeqSerializer<'a, 'k, W: 'a> {
+    out: Output<'a, W>,
+    deferred: &'a mut Vec<Item<'static>>,
+    config: &'a Config,
+    section: Option<&'k str>,
+    key: &'k str,
+    top_level: bool,
+    allow_values: &'a mut bool,
+    list: Option<Vec<String>>,
+}
+
+impl<'a, 'k, W: Write> ser::SerializeSeq for SeqSerializer<'a, 'k, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(ValueSerializer {
+            out: self.out.reborrow(),
+            deferred: self.deferred,
+            config: self.config,
+            section: self.section,
+            key: self.key,
+            top_level: self.top_level,
+            allow_values: self.allow_values,
+            element: true,
+            list: self.list.as_mut(),
+        })
+    }
+
+    fn end(self) -> Result<()> {
+        match (self.list, self.config.list_separator) {
+            (Some(list), Some(separator)) => ValueSerializer {
+                out: self.out,
+                deferred: self.deferred,
+                config: self.config,
+                section: self.section,
+                key: self.key,
+                top_level: self.top_level,
+                allow_values: self.allow_values,
+                element: false,
+                list: None,
+            }.serialize_string(join_list(list, separator)),
+            _ => Ok(()),
+        }
     }
 }
 
-#[derive(Default)]
-struct KeySerializer {
+impl<'a, 'k, W: Write> ser::SerializeTuple for SeqSerializer<'a, 'k, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+impl<'a, 'k, W: Write> ser::SerializeTupleStruct for SeqSerializer<'a, 'k, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        ser::SerializeSeq::end(self)
+    }
+}
+
+struct KeySerializer<'a> {
+    config: &'a Config,
     key: String,
 }
 
-impl<'a> ser::Serializer for &'a mut KeySerializer {
+impl<'a> KeySerializer<'a> {
+    fn new(config: &'a Config) -> Self {
+        KeySerializer {
+            config,
+            key: String::new(),
+        }
+    }
+}
+
+impl<'a> ser::Serializer for &mut KeySerializer<'a> {
     type Ok = ();
     type Error = Error;
 
@@ -277,48 +640,49 @@ impl<'a> ser::Serializer for &'a mut KeySerializer {
     type SerializeStruct = Impossible<Self::Ok, Self::Error>;
     type SerializeStructVariant = Impossible<Self::Ok, Self::Error>;
 
-    fn serialize_bool(self, _v: bool) -> Result<()> {
-        Err(Error::NonStringKey)
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.key = if v { &self.config.true_value } else { &self.config.false_value }.clone();
+        Ok(())
     }
 
-    fn serialize_i8(self, _v: i8) -> Result<()> {
-        Err(Error::NonStringKey)
+    fn serialize_i8(self, v: i8) -> Result<()> {
+        self.serialize_str(&v.to_string())
     }
 
-    fn serialize_i16(self, _v: i16) -> Result<()> {
-        Err(Error::NonStringKey)
+    fn serialize_i16(self, v: i16) -> Result<()> {
+        self.serialize_str(&v.to_string())
     }
 
-    fn serialize_i32(self, _v: i32) -> Result<()> {
-        Err(Error::NonStringKey)
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        self.serialize_str(&v.to_string())
     }
 
-    fn serialize_i64(self, _v: i64) -> Result<()> {
-        Err(Error::NonStringKey)
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        self.serialize_str(&v.to_string())
     }
 
-    fn serialize_u8(self, _v: u8) -> Result<()> {
-        Err(Error::NonStringKey)
+    fn serialize_u8(self, v: u8) -> Result<()> {
+        self.serialize_str(&v.to_string())
     }
 
-    fn serialize_u16(self, _v: u16) -> Result<()> {
-        Err(Error::NonStringKey)
+    fn serialize_u16(self, v: u16) -> Result<()> {
+        self.serialize_str(&v.to_string())
     }
 
-    fn serialize_u32(self, _v: u32) -> Result<()> {
-        Err(Error::NonStringKey)
+    fn serialize_u32(self, v: u32) -> Result<()> {
+        self.serialize_str(&v.to_string())
     }
 
-    fn serialize_u64(self, _v: u64) -> Result<()> {
-        Err(Error::NonStringKey)
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.serialize_str(&v.to_string())
     }
 
-    fn serialize_f32(self, _v: f32) -> Result<()> {
-        Err(Error::NonStringKey)
+    fn serialize_f32(self, v: f32) -> Result<()> {
+        self.serialize_str(&v.to_string())
     }
 
-    fn serialize_f64(self, _v: f64) -> Result<()> {
-        Err(Error::NonStringKey)
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        self.serialize_str(&v.to_string())
     }
 
     fn serialize_char(self, v: char) -> Result<()> {
@@ -326,7 +690,8 @@ impl<'a> ser::Serializer for &'a mut KeySerializer {
     }
 
     fn serialize_str(self, v: &str) -> Result<()> {
-        Ok(self.key = v.into())
+        self.key = v.into();
+        Ok(())
     }
 
     fn serialize_bytes(self, _v: &[u8]) -> Result<()> {
@@ -394,7 +759,7 @@ impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
     type Ok = ();
     type Error = Error;
 
-    type SerializeSeq = Impossible<Self::Ok, Self::Error>;
+    type SerializeSeq = TopSeqSerializer<'a, W>;
     type SerializeTuple = Impossible<Self::Ok, Self::Error>;
     type SerializeTupleStruct = Impossible<Self::Ok, Self::Error>;
     type SerializeTupleVariant = Impossible<Self::Ok, Self::Error>;
@@ -487,15 +852,20 @@ impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
     }
 
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
-        Err(Error::TopLevelMap)
+        let (out, config) = self.output();
+        Ok(TopSeqSerializer {
+            out,
+            config,
+            allow_values: true,
+        })
     }
 
-    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
-        self.serialize_seq(Some(len))
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Err(Error::TopLevelMap)
     }
 
-    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct> {
-        self.serialize_seq(Some(len))
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeTupleStruct> {
+        Err(Error::TopLevelMap)
     }
 
     fn serialize_tuple_variant(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant> {
@@ -503,11 +873,18 @@ impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
     }
 
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        let (out, config) = self.output();
         Ok(MapSerializer {
-            writer: &mut self.writer,
+            out,
+            deferred: Vec::new(),
+            config,
+            section: None,
+            prefix: String::new(),
             key: None,
             top_level: true,
             allow_values: true,
+            keys: Vec::new(),
+            skip: false,
         })
     }
 
@@ -520,30 +897,403 @@ impl<'a, W: Write> ser::Serializer for &'a mut Serializer<W> {
     }
 }
 
+/// Serializes a sequence of enums or named sections as the top level of a document
+///
+/// Struct variants are written as a section named after the variant, while newtype variants are
+/// written as a top-level `variant=value`, or as a section if the value is a map or struct. Pairs
+/// are written in the same way, named by their first element.
+pub struct TopSeqSerializer<'a, W: 'a> {
+    out: Output<'a, W>,
+    config: &'a Config,
+    allow_values: bool,
+}
+
+impl<'a, W: Write> ser::SerializeSeq for TopSeqSerializer<'a, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(TopElementSerializer {
+            out: self.out.reborrow(),
+            config: self.config,
+            allow_values: &mut self.allow_values,
+        })
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+struct TopElementSerializer<'a, W: 'a> {
+    out: Output<'a, W>,
+    config: &'a Config,
+    allow_values: &'a mut bool,
+}
+
+impl<'a, W: Write + 'a> ser::Serializer for TopElementSerializer<'a, W> {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Impossible<Self::Ok, Self::Error>;
+    type SerializeTuple = TopPairSerializer<'a, W>;
+    type SerializeTupleStruct = TopPairSerializer<'a, W>;
+    type SerializeTupleVariant = Impossible<Self::Ok, Self::Error>;
+    type SerializeMap = TopSectionSerializer<'a, W>;
+    type SerializeStruct = TopSectionSerializer<'a, W>;
+    type SerializeStructVariant = MapSerializer<'a, W>;
+
+    fn serialize_bool(self, _v: bool) -> Result<()> {
+        Err(Error::TopLevelMap)
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<()> {
+        Err(Error::TopLevelMap)
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<()> {
+        Err(Error::TopLevelMap)
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<()> {
+        Err(Error::TopLevelMap)
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<()> {
+        Err(Error::TopLevelMap)
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<()> {
+        Err(Error::TopLevelMap)
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<()> {
+        Err(Error::TopLevelMap)
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<()> {
+        Err(Error::TopLevelMap)
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<()> {
+        Err(Error::TopLevelMap)
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<()> {
+        Err(Error::TopLevelMap)
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<()> {
+        Err(Error::TopLevelMap)
+    }
+
+    fn serialize_char(self, _v: char) -> Result<()> {
+        Err(Error::TopLevelMap)
+    }
+
+    fn serialize_str(self, _v: &str) -> Result<()> {
+        Err(Error::TopLevelMap)
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<()> {
+        Err(Error::TopLevelMap)
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        Err(Error::TopLevelMap)
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        Err(Error::TopLevelMap)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        Err(Error::TopLevelMap)
+    }
+
+    fn serialize_unit_variant(self, _name: &'static str, _variant_index: u32, _variant: &'static str) -> Result<()> {
+        Err(UnsupportedType::Unit.into())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(self, _name: &'static str, value: &T) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(self, _name: &'static str, _variant_index: u32, variant: &'static str, value: &T) -> Result<()> {
+        value.serialize(ValueSerializer {
+            out: self.out,
+            deferred: &mut Vec::new(),
+            config: self.config,
+            section: None,
+            key: variant,
+            top_level: true,
+            allow_values: self.allow_values,
+            element: false,
+            list: None,
+        })
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Err(Error::TopLevelMap)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Ok(TopPairSerializer {
+            out: self.out,
+            config: self.config,
+            allow_values: self.allow_values,
+            name: None,
+            written: false,
+        })
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeTupleStruct> {
+        self.serialize_tuple(len)
+    }
+
+    fn serialize_tuple_variant(self, _name: &'static str, _variant_index: u32, _variant: &'static str, _len: usize) -> Result<Self::SerializeTupleVariant> {
+        Err(UnsupportedType::Seq.into())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        match self.config.section_name_key {
+            Some(..) => Ok(TopSectionSerializer {
+                out: Some(self.out),
+                config: self.config,
+                allow_values: self.allow_values,
+                body: None,
+            }),
+            None => Err(Error::SectionName),
+        }
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Self::SerializeStruct> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(mut self, _name: &'static str, _variant_index: u32, variant: &'static str, _len: usize) -> Result<Self::SerializeStructVariant> {
+        *self.allow_values = false;
+        self.out.write(Item::Section {
+            name: variant.into(),
+        })?;
+
+        Ok(MapSerializer {
+            out: self.out,
+            deferred: Vec::new(),
+            config: self.config,
+            section: Some(variant.into()),
+            prefix: String::new(),
+            key: None,
+            top_level: false,
+            allow_values: false,
+            keys: Vec::new(),
+            skip: false,
+        })
+    }
+}
+
+/// Serializes a `(name, value)` pair in a top-level sequence as `name=value`, or as a section if
+/// the value is a map or struct
+pub struct TopPairSerializer<'a, W: 'a> {
+    out: Output<'a, W>,
+    config: &'a Config,
+    allow_values: &'a mut bool,
+    name: Option<String>,
+    written: bool,
+}
+
+impl<'a, W: Write> ser::SerializeTuple for TopPairSerializer<'a, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        let name = match (self.name.as_ref(), self.written) {
+            (None, _) => {
+                let mut k = KeySerializer::new(self.config);
+                value.serialize(&mut k)?;
+                self.name = Some(k.key);
+                return Ok(())
+            },
+            (Some(name), false) => name,
+            (Some(..), true) => return Err(Error::TopLevelMap),
+        };
+
+        self.written = true;
+        value.serialize(ValueSerializer {
+            out: self.out.reborrow(),
+            deferred: &mut Vec::new(),
+            config: self.config,
+            section: None,
+            key: name,
+            top_level: true,
+            allow_values: self.allow_values,
+            element: false,
+            list: None,
+        })
+    }
+
+    fn end(self) -> Result<()> {
+        if self.written {
+            Ok(())
+        } else {
+            Err(Error::TopLevelMap)
+        }
+    }
+}
+
+impl<'a, W: Write> ser::SerializeTupleStruct for TopPairSerializer<'a, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        ser::SerializeTuple::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<()> {
+        ser::SerializeTuple::end(self)
+    }
+}
+
+/// Serializes a map or struct in a top-level sequence as a section, named by the value of its
+/// first key
+pub struct TopSectionSerializer<'a, W: 'a> {
+    /// Taken once the section header has been written
+    out: Option<Output<'a, W>>,
+    config: &'a Config,
+    allow_values: &'a mut bool,
+    body: Option<MapSerializer<'a, W>>,
+}
+
+impl<'a, W: Write> ser::SerializeMap for TopSectionSerializer<'a, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
+        if let Some(ref mut body) = self.body {
+            return ser::SerializeMap::serialize_key(body, key)
+        }
+
+        let mut k = KeySerializer::new(self.config);
+        key.serialize(&mut k)?;
+        if Some(&k.key) == self.config.section_name_key.as_ref() {
+            Ok(())
+        } else {
+            Err(Error::SectionName)
+        }
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        if let Some(ref mut body) = self.body {
+            return ser::SerializeMap::serialize_value(body, value)
+        }
+
+        let mut name = KeySerializer::new(self.config);
+        value.serialize(&mut name)?;
+        let mut out = self.out.take().ok_or(Error::MapKeyMissing)?;
+        *self.allow_values = false;
+        out.write(Item::Section {
+            name: name.key.clone().into(),
+        })?;
+
+        self.body = Some(MapSerializer {
+            out,
+            deferred: Vec::new(),
+            config: self.config,
+            section: Some(name.key),
+            prefix: String::new(),
+            key: None,
+            top_level: false,
+            allow_values: false,
+            keys: Vec::new(),
+            skip: false,
+        });
+        Ok(())
+    }
+
+    fn end(self) -> Result<()> {
+        match self.body {
+            Some(body) => ser::SerializeMap::end(body),
+            None => Err(Error::SectionName),
+        }
+    }
+}
+
+impl<'a, W: Write> ser::SerializeStruct for TopSectionSerializer<'a, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        ser::SerializeMap::serialize_entry(self, key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        ser::SerializeMap::end(self)
+    }
+}
+
 impl<'a, W: Write> ser::SerializeMap for MapSerializer<'a, W> {
     type Ok = ();
     type Error = Error;
 
     fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<()> {
-        let mut k = KeySerializer::default();
+        let mut k = KeySerializer::new(self.config);
         key.serialize(&mut k)?;
-        self.key = Some(k.key);
+        let key = format!("{}{}", self.prefix, k.key);
+
+        match (self.keys.iter().position(|k| same_key(&k.0, &key)), self.config.duplicate_keys) {
+            (Some(..), DuplicateKeys::Error) => return Err(Error::DuplicateKey(key)),
+            (Some(..), DuplicateKeys::First) => self.skip = true,
+            (Some(i), DuplicateKeys::Last) => {
+                self.keys.remove(i);
+            },
+            _ => (),
+        }
+        if !self.skip && self.config.duplicate_keys != DuplicateKeys::Collect {
+            self.keys.push((key.clone(), Vec::new(), Vec::new()));
+        }
+
+        self.key = Some(key);
         Ok(())
     }
 
     fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
-        let writer = &mut self.writer;
-        let allow_values = &mut self.allow_values;
-        let top_level = self.top_level;
-        self.key.as_ref().ok_or_else(|| Error::MapKeyMissing).and_then(move |key| value.serialize(ValueSerializer {
-            writer: writer,
-            key: key,
-            top_level: top_level,
-            allow_values: allow_values,
-        }))
+        let key = self.key.as_ref().ok_or(Error::MapKeyMissing)?;
+        if self.skip {
+            self.skip = false;
+            return Ok(())
+        }
+
+        let (out, deferred) = match (self.config.duplicate_keys, self.keys.last_mut()) {
+            (DuplicateKeys::Last, Some(&mut (_, ref mut values, ref mut deferred))) => (Output::Items(values), deferred),
+            _ => (self.out.reborrow(), &mut self.deferred),
+        };
+        value.serialize(ValueSerializer {
+            out,
+            deferred,
+            config: self.config,
+            section: self.section.as_ref().map(|s| &s[..]),
+            key,
+            top_level: self.top_level,
+            allow_values: &mut self.allow_values,
+            element: false,
+            list: None,
+        })
     }
 
-    fn end(self) -> Result<()> {
+    fn end(mut self) -> Result<()> {
+        for (_, values, mut deferred) in self.keys.drain(..) {
+            for item in values {
+                self.out.write(item)?;
+            }
+            self.deferred.append(&mut deferred);
+        }
+        for item in self.deferred.drain(..) {
+            self.out.write(item)?;
+        }
         Ok(())
     }
 }
@@ -553,16 +1303,39 @@ impl<'a, W: Write> ser::SerializeStruct for MapSerializer<'a, W> {
     type Error = Error;
 
     fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        let key = if self.prefix.is_empty() {
+            Cow::Borrowed(key)
+        } else {
+            Cow::Owned(format!("{}{}", self.prefix, key))
+        };
         value.serialize(ValueSerializer {
-            writer: &mut self.writer,
-            key: key,
+            out: self.out.reborrow(),
+            deferred: &mut self.deferred,
+            config: self.config,
+            section: self.section.as_ref().map(|s| &s[..]),
+            key: &key,
             top_level: self.top_level,
             allow_values: &mut self.allow_values,
+            element: false,
+            list: None,
         })
     }
 
     fn end(self) -> Result<()> {
-        Ok(())
+        ser::SerializeMap::end(self)
+    }
+}
+
+impl<'a, W: Write> ser::SerializeStructVariant for MapSerializer<'a, W> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, key: &'static str, value: &T) -> Result<()> {
+        ser::SerializeStruct::serialize_field(self, key, value)
+    }
+
+    fn end(self) -> Result<()> {
+        ser::SerializeMap::end(self)
     }
 }
 
@@ -572,6 +1345,11 @@ pub fn to_writer<W: Write, T: Serialize + ?Sized>(writer: W, value: &T) -> Resul
     value.serialize(&mut ser)
 }
 
+/// Serializes `value` into an existing document, as described by `Serializer::update_document`.
+pub fn to_document<T: Serialize + ?Sized>(document: &mut Document, value: &T) -> Result<()> {
+    Serializer::new(Writer::new(io::sink(), Default::default())).update_document(document, value)
+}
+
 pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>> {
     let mut writer = Vec::with_capacity(128);
     to_writer(&mut writer, value).map(|_| writer)
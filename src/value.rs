@@ -0,0 +1,327 @@
+use std::{fmt, io, iter, mem, result, vec};
+use serde::de::{self, Deserialize, DeserializeOwned, Visitor, MapAccess, SeqAccess};
+use serde::ser::{self, Serialize, SerializeMap};
+use void::Void;
+use parse::Item;
+use write::Writer;
+use de::{self as ini_de, Deserializer};
+use ser::{self as ini_ser, Serializer};
+
+type Items<'de> = iter::Map<vec::IntoIter<Item<'de>>, fn(Item<'de>) -> result::Result<Item<'de>, Void>>;
+
+/// An INI document held as sections and values, without a type of its own
+///
+/// Keys that are repeated, as sequences are, appear once per element. A `Value` can be
+/// deserialized from any document and serialized back to it, and can itself be deserialized into
+/// another type as a document would be.
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+pub struct Value {
+    /// The top-level keys, which precede the first section
+    pub global: Vec<(String, String)>,
+    /// Each section header in order, along with its keys
+    pub sections: Vec<(String, Vec<(String, String)>)>,
+}
+
+impl Value {
+    pub fn new() -> Self {
+        Value::default()
+    }
+
+    /// Returns the keys of the first section called `name`.
+    pub fn section(&self, name: &str) -> Option<&[(String, String)]> {
+        self.sections.iter().find(|s| s.0 == name).map(|s| &s.1[..])
+    }
+
+    /// Returns the value of the first occurrence of a key, taking top-level keys from a section
+    /// of `None`.
+    pub fn get(&self, section: Option<&str>, key: &str) -> Option<&str> {
+        let entries = match section {
+            Some(name) => self.section(name)?,
+            None => &self.global[..],
+        };
+        entries.iter().find(|e| e.0 == key).map(|e| &e.1[..])
+    }
+
+    /// Sets the value of a key, adding it and its section if they are not present.
+    ///
+    /// Any repetitions of the key are removed. Returns the previous value, if any.
+    pub fn set<K: Into<String>, V: Into<String>>(&mut self, section: Option<&str>, key: K, value: V) -> Option<String> {
+        let (key, value) = (key.into(), value.into());
+        let entries = self.entries_mut(section);
+        match entries.iter().position(|e| e.0 == key) {
+            Some(i) => {
+                let previous = mem::replace(&mut entries[i].1, value);
+                let mut index = 0;
+                entries.retain(|e| {
+                    index += 1;
+                    index <= i + 1 || e.0 != key
+                });
+                Some(previous)
+            },
+            None => {
+                entries.push((key, value));
+                None
+            },
+        }
+    }
+
+    /// Removes a key and its repetitions from the first section called `section`, as `get` finds
+    /// it, and returns the first of their values. A missing section is not added.
+    pub fn remove(&mut self, section: Option<&str>, key: &str) -> Option<String> {
+        let entries = match section {
+            Some(name) => &mut self.sections.iter_mut().find(|s| s.0 == name)?.1,
+            None => &mut self.global,
+        };
+        let previous = entries.iter().find(|e| e.0 == key).map(|e| e.1.clone());
+        entries.retain(|e| e.0 != key);
+        previous
+    }
+
+    fn entries_mut(&mut self, section: Option<&str>) -> &mut Vec<(String, String)> {
+        let name = match section {
+            Some(name) => name,
+            None => return &mut self.global,
+        };
+        let i = match self.sections.iter().position(|s| s.0 == name) {
+            Some(i) => i,
+            None => {
+                self.sections.push((name.into(), Vec::new()));
+                self.sections.len() - 1
+            },
+        };
+        &mut self.sections[i].1
+    }
+
+    fn from_items<'a, I: IntoIterator<Item=Item<'a>>>(items: I) -> Self {
+        let mut value = Value::new();
+        for item in items {
+            match item {
+                Item::Section { name } => value.sections.push((name.into_owned(), Vec::new())),
+                Item::Value { key, value: v } => match value.sections.last_mut() {
+                    Some(section) => section.1.push((key.into_owned(), v.into_owned())),
+                    None => value.global.push((key.into_owned(), v.into_owned())),
+                },
+                Item::Comment { .. } | Item::Empty => (),
+            }
+        }
+        value
+    }
+
+    fn into_items(self) -> Vec<Item<'static>> {
+        let values = |entries: Vec<(String, String)>| entries.into_iter().map(|(key, value)| Item::Value { key: key.into(), value: value.into() });
+        let mut items: Vec<_> = values(self.global).collect();
+        for (name, entries) in self.sections {
+            items.push(Item::Section { name: name.into() });
+            items.extend(values(entries));
+        }
+        items
+    }
+
+    fn into_deserializer<'de>(self) -> Deserializer<'de, Items<'de>> {
+        Deserializer::new(self.into_items().into_iter().map(Ok as fn(_) -> _))
+    }
+}
+
+/// Groups repeated keys together, in the order each first appears.
+fn group(entries: &[(String, String)]) -> Vec<(&str, Vec<&str>)> {
+    let mut groups: Vec<(&str, Vec<&str>)> = Vec::new();
+    for (key, value) in entries {
+        match groups.iter_mut().find(|g| g.0 == key) {
+            Some(group) => group.1.push(value),
+            None => groups.push((key, vec![value])),
+        }
+    }
+    groups
+}
+
+/// The values of a key, written as a sequence if it is repeated
+struct Values<'a>(Vec<&'a str>);
+
+impl<'a> Serialize for Values<'a> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        match self.0[..] {
+            [value] => serializer.serialize_str(value),
+            ref values => serializer.collect_seq(values),
+        }
+    }
+}
+
+struct Body<'a>(&'a [(String, String)]);
+
+impl<'a> Serialize for Body<'a> {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        let groups = group(self.0);
+        let mut map = serializer.serialize_map(Some(groups.len()))?;
+        for (key, values) in groups {
+            map.serialize_entry(key, &Values(values))?;
+        }
+        map.end()
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: ser::Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        let groups = group(&self.global);
+        let mut map = serializer.serialize_map(Some(groups.len() + self.sections.len()))?;
+        for (key, values) in groups {
+            map.serialize_entry(key, &Values(values))?;
+        }
+        for (name, entries) in &self.sections {
+            map.serialize_entry(name, &Body(entries))?;
+        }
+        map.end()
+    }
+}
+
+/// The value of a top-level key, which is either a section or the values of a key
+enum Node {
+    Values(Vec<String>),
+    Section(Vec<(String, String)>),
+}
+
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> result::Result<Self, D::Error> {
+        struct NodeVisitor;
+
+        impl<'de> Visitor<'de> for NodeVisitor {
+            type Value = Node;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a string, a sequence of strings, or a section")
+            }
+
+            fn visit_bool<E: de::Error>(self, v: bool) -> result::Result<Node, E> {
+                Ok(Node::Values(vec![v.to_string()]))
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> result::Result<Node, E> {
+                Ok(Node::Values(vec![v.to_string()]))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> result::Result<Node, E> {
+                Ok(Node::Values(vec![v.to_string()]))
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> result::Result<Node, E> {
+                Ok(Node::Values(vec![v.to_string()]))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> result::Result<Node, E> {
+                Ok(Node::Values(vec![v.into()]))
+            }
+
+            fn visit_string<E: de::Error>(self, v: String) -> result::Result<Node, E> {
+                Ok(Node::Values(vec![v]))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> result::Result<Node, A::Error> {
+                let mut values = Vec::new();
+                while let Some(node) = seq.next_element()? {
+                    match node {
+                        Node::Values(mut v) if v.len() == 1 => values.append(&mut v),
+                        _ => return Err(de::Error::invalid_type(de::Unexpected::Seq, &"a string")),
+                    }
+                }
+                Ok(Node::Values(values))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> result::Result<Node, A::Error> {
+                let mut entries = Vec::new();
+                while let Some((key, node)) = map.next_entry::<String, Node>()? {
+                    match node {
+                        Node::Values(values) => entries.extend(values.into_iter().map(|v| (key.clone(), v))),
+                        Node::Section(..) => return Err(de::Error::invalid_type(de::Unexpected::Map, &"a string or a sequence of strings")),
+                    }
+                }
+                Ok(Node::Section(entries))
+            }
+        }
+
+        deserializer.deserialize_any(NodeVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: de::Deserializer<'de>>(deserializer: D) -> result::Result<Self, D::Error> {
+        struct ValueVisitor;
+
+        impl<'de> Visitor<'de> for ValueVisitor {
+            type Value = Value;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "an INI document")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> result::Result<Value, A::Error> {
+                let mut value = Value::new();
+                while let Some((key, node)) = map.next_entry::<String, Node>()? {
+                    match node {
+                        Node::Values(values) => value.global.extend(values.into_iter().map(|v| (key.clone(), v))),
+                        Node::Section(entries) => value.sections.push((key, entries)),
+                    }
+                }
+                Ok(value)
+            }
+        }
+
+        deserializer.deserialize_map(ValueVisitor)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for Value {
+    type Error = ini_de::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> ini_de::Result<V::Value> {
+        (&mut self.into_deserializer()).deserialize_any(visitor)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> ini_de::Result<V::Value> {
+        (&mut self.into_deserializer()).deserialize_seq(visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(self, name: &'static str, fields: &'static [&'static str], visitor: V) -> ini_de::Result<V::Value> {
+        (&mut self.into_deserializer()).deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> ini_de::Result<V::Value> {
+        visitor.visit_some(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes
+        byte_buf unit unit_struct newtype_struct tuple tuple_struct
+        map identifier ignored_any enum
+    }
+}
+
+/// Adds the lines that `value` would be written as under `key` to `entries`, for `ini!`.
+#[doc(hidden)]
+pub fn insert_serialized<T: Serialize + ?Sized>(entries: &mut Vec<(String, String)>, key: &str, value: &T) {
+    struct Single<'a, T: ?Sized + 'a>(&'a str, &'a T);
+
+    impl<'a, T: Serialize + ?Sized> Serialize for Single<'a, T> {
+        fn serialize<S: ser::Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+            let mut map = serializer.serialize_map(Some(1))?;
+            map.serialize_entry(self.0, self.1)?;
+            map.end()
+        }
+    }
+
+    match to_value(&Single(key, value)) {
+        Ok(Value { global, ref sections }) if sections.is_empty() => entries.extend(global),
+        Ok(..) => panic!("ini! value for `{}` is not a single key", key),
+        Err(e) => panic!("ini! value for `{}` cannot be serialized: {}", key, e),
+    }
+}
+
+/// Serializes `value` into a `Value`, as it would be written by `to_string`.
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> ini_ser::Result<Value> {
+    Serializer::new(Writer::new(io::sink(), Default::default()))
+        .serialize_items(value)
+        .map(Value::from_items)
+}
+
+/// Deserializes a `T` from a `Value`, as it would be from the document the value holds.
+pub fn from_value<T: DeserializeOwned>(value: Value) -> ini_de::Result<T> {
+    T::deserialize(value)
+}
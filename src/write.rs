@@ -2,18 +2,13 @@ use parse::Item;
 use std::io::{self, Write};
 use std::fmt;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub enum LineEnding {
     Linefeed,
+    #[default]
     CrLf,
 }
 
-impl Default for LineEnding {
-    fn default() -> Self {
-        LineEnding::CrLf
-    }
-}
-
 impl fmt::Display for LineEnding {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", match *self {
@@ -23,20 +18,58 @@ impl fmt::Display for LineEnding {
     }
 }
 
+/// Joins list elements into a single delimited value, the inverse of `parse::split_list`.
+///
+/// Elements that are empty, have surrounding whitespace, or contain the separator, a quote, or a
+/// backslash are written in double quotes.
+pub fn join_list<I: IntoIterator<Item=S>, S: AsRef<str>>(values: I, separator: char) -> String {
+    let mut out = String::new();
+    for (i, value) in values.into_iter().enumerate() {
+        let value = value.as_ref();
+        if i > 0 {
+            out.push(separator);
+        }
+
+        let quote = value.is_empty() || value.trim() != value ||
+            value.contains(&[separator, '"', '\\'][..]);
+        if quote {
+            out.push('"');
+            for c in value.chars() {
+                if c == '"' || c == '\\' {
+                    out.push('\\');
+                }
+                out.push(c);
+            }
+            out.push('"');
+        } else {
+            out.push_str(value);
+        }
+    }
+    out
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct Writer<W> {
     write: W,
     line_ending: LineEnding,
+    comment_prefix: char,
 }
 
 impl<W> Writer<W> {
     pub fn new(write: W, line_ending: LineEnding) -> Self {
         Writer {
-            write: write,
-            line_ending: line_ending,
+            write,
+            line_ending,
+            comment_prefix: ';',
         }
     }
 
+    /// Sets the prefix written for comments that do not specify their own, `;` by default.
+    pub fn comment_prefix(mut self, prefix: char) -> Self {
+        self.comment_prefix = prefix;
+        self
+    }
+
     pub fn into_inner(self) -> W {
         self.write
     }
@@ -47,7 +80,7 @@ impl<W: Write> Writer<W> {
         match *item {
             Item::Section { ref name } => write!(&mut self.write, "[{}]{}", name, self.line_ending),
             Item::Value { ref key, ref value } => write!(&mut self.write, "{}={}{}", key, value, self.line_ending),
-            Item::Comment { ref text } => write!(&mut self.write, ";{}{}", text, self.line_ending),
+            Item::Comment { prefix, ref text } => write!(&mut self.write, "{}{}{}", prefix.unwrap_or(self.comment_prefix), text, self.line_ending),
             Item::Empty => write!(&mut self.write, "{}", self.line_ending),
         }
     }
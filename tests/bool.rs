@@ -0,0 +1,65 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_ini;
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use serde_ini::{Deserializer, Serializer, Writer, LineEnding};
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Default, Debug)]
+struct Flags {
+    a: bool,
+    b: bool,
+    c: bool,
+    d: bool,
+}
+
+const TEST_INPUT: &str = "
+a=true
+b=No
+c=ON
+d=0
+";
+
+#[test]
+fn bool_de() {
+    let expected = Flags { a: true, b: false, c: true, d: false };
+    assert_eq!(expected, serde_ini::from_str::<Flags>(TEST_INPUT).unwrap());
+
+    assert!(serde_ini::from_str::<Flags>("a=maybe\nb=0\nc=0\nd=0").is_err());
+}
+
+#[test]
+fn bool_de_custom() {
+    let mut de = Deserializer::from_str("a=Y\nb=n\nc=y\nd=N").bool_values(&["y"], &["n"]);
+    let expected = Flags { a: true, b: false, c: true, d: false };
+    assert_eq!(expected, Flags::deserialize(&mut de).unwrap());
+
+    let mut de = Deserializer::from_str(TEST_INPUT).bool_values(&["y"], &["n"]);
+    assert!(Flags::deserialize(&mut de).is_err());
+}
+
+#[test]
+fn bool_en() {
+    let model = Flags { a: true, b: false, c: false, d: true };
+
+    assert_eq!("a=true\r\nb=false\r\nc=false\r\nd=true\r\n", serde_ini::to_string(&model).unwrap());
+
+    let mut data = Vec::new();
+    model.serialize(&mut Serializer::new(Writer::new(&mut data, LineEnding::Linefeed)).bool_values("yes", "no")).unwrap();
+    assert_eq!(&b"a=yes\nb=no\nc=no\nd=yes\n"[..], &data[..]);
+    assert_eq!(model, serde_ini::from_read::<_, Flags>(&data[..]).unwrap());
+}
+
+#[test]
+fn bool_en_keys() {
+    let mut model = BTreeMap::new();
+    model.insert(true, 1);
+    model.insert(false, 0);
+
+    let mut data = Vec::new();
+    model.serialize(&mut Serializer::new(Writer::new(&mut data, LineEnding::Linefeed)).bool_values("on", "off")).unwrap();
+    assert_eq!(&b"off=0\non=1\n"[..], &data[..]);
+    assert_eq!(model, serde_ini::from_read::<_, BTreeMap<bool, u32>>(&data[..]).unwrap());
+}
@@ -0,0 +1,61 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_ini;
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use serde_ini::{Parser, Item};
+
+#[derive(Deserialize, Debug)]
+struct Server<'a> {
+    host: &'a str,
+    #[serde(borrow)]
+    name: Cow<'a, str>,
+    port: u16,
+}
+
+#[derive(Deserialize, Debug)]
+struct TestModel<'a> {
+    #[serde(borrow)]
+    server: Server<'a>,
+    #[serde(borrow)]
+    env: BTreeMap<&'a str, &'a str>,
+}
+
+const TEST_INPUT: &str = "\
+[server]
+host = localhost
+name=app
+port=80
+
+[env]
+PATH=/bin
+HOME = /root
+";
+
+#[test]
+fn borrow_fields() {
+    let model: TestModel = serde_ini::from_str(TEST_INPUT).unwrap();
+    assert_eq!("localhost", model.server.host);
+    assert_eq!(80, model.server.port);
+    match model.server.name {
+        Cow::Borrowed(name) => assert_eq!("app", name),
+        Cow::Owned(..) => panic!("name was copied"),
+    }
+
+    let env: Vec<_> = model.env.into_iter().collect();
+    assert_eq!(vec![("HOME", "/root"), ("PATH", "/bin")], env);
+}
+
+#[test]
+fn borrow_items() {
+    let items = Parser::from_str("[server]\nhost = localhost\n").map(|item| item.unwrap().1).collect::<Vec<_>>();
+    match items[1] {
+        Item::Value { key: Cow::Borrowed(key), value: Cow::Borrowed(value) } => assert_eq!(("host", "localhost"), (key, value)),
+        ref item => panic!("unexpected item {:?}", item),
+    }
+
+    let items: Vec<Item<'static>> = items.into_iter().map(Item::into_owned).collect();
+    assert_eq!(Item::Section { name: "server".into() }, items[0]);
+}
@@ -0,0 +1,115 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_ini;
+
+use serde_ini::{Deserializer, Parser, Item, Position, DuplicateKeys};
+use serde_ini::de::ErrorKind;
+
+#[derive(Deserialize, Clone, PartialEq, Default, Debug)]
+#[serde(deny_unknown_fields)]
+struct Section {
+    host: String,
+    port: Option<u16>,
+    #[serde(default)]
+    debug: bool,
+}
+
+#[derive(Deserialize, Clone, PartialEq, Default, Debug)]
+struct TestModel {
+    name: String,
+    server: Section,
+}
+
+const TEST_INPUT: &str = "\
+name=app
+[server
+[server]
+host=localhost
+port=http
+hots=typo
+debug=maybe
+";
+
+#[test]
+fn collect_parser() {
+    let items: Vec<_> = Parser::from_str("[a\nkey=value\nkey\n").collect();
+    assert_eq!(3, items.len());
+    assert_eq!(Some(Position { line: 1, column: 1 }), items[0].as_ref().unwrap_err().position());
+    assert_eq!(&(Position { line: 2, column: 1 }, Item::Value { key: "key".into(), value: "value".into() }), items[1].as_ref().unwrap());
+    assert_eq!(Some(Position { line: 3, column: 1 }), items[2].as_ref().unwrap_err().position());
+}
+
+#[test]
+fn collect_de() {
+    let mut de = Deserializer::from_str(TEST_INPUT).file_name("app.ini");
+    let (value, errors) = de.deserialize_collecting::<TestModel>();
+    assert_eq!(Some(TestModel {
+        name: "app".into(),
+        server: Section {
+            host: "localhost".into(),
+            port: None,
+            debug: false,
+        },
+    }), value);
+
+    let errors: Vec<_> = errors.iter().map(ToString::to_string).collect();
+    assert_eq!(vec![
+        "INI syntax error: section missing ']' at line 2, column 1 of app.ini",
+        "in [server] port = \"http\": invalid value: string \"http\", expected u16 (invalid digit found in string) at line 5, column 1 of app.ini",
+        "in [server] hots = \"typo\": unknown field `hots`, expected one of `host`, `port`, `debug` at line 6, column 1 of app.ini",
+        "in [server] debug = \"maybe\": invalid value: string \"maybe\", expected a boolean at line 7, column 1 of app.ini",
+    ], errors);
+
+    // a valid document has no errors
+    let (value, errors) = Deserializer::from_str("name=app\n[server]\nhost=h\n").deserialize_collecting::<TestModel>();
+    assert_eq!("h", value.unwrap().server.host);
+    assert!(errors.is_empty());
+}
+
+#[derive(Deserialize, Debug)]
+struct Required {
+    port: u16,
+    host: u16,
+}
+
+#[test]
+fn collect_de_required() {
+    // skipping an invalid required field leaves no value, but it is not also reported as missing
+    let (value, errors) = Deserializer::from_str("port=x\nhost=y\n").deserialize_collecting::<Required>();
+    assert!(value.is_none());
+    let keys: Vec<_> = errors.iter().map(|e| match *e.kind() {
+        ErrorKind::InvalidValue { .. } => e.key().unwrap(),
+        ref kind => panic!("unexpected {:?}", kind),
+    }).collect();
+    assert_eq!(vec!["port", "host"], keys);
+
+    let (value, errors) = Deserializer::from_str("port=1\n").deserialize_collecting::<Required>();
+    assert!(value.is_none());
+    match *errors[0].kind() {
+        ErrorKind::MissingField("host") => (),
+        ref kind => panic!("unexpected {:?}", kind),
+    }
+
+    let (value, _) = Deserializer::from_str("port=1\nhost=2\n").deserialize_collecting::<Required>();
+    let value = value.unwrap();
+    assert_eq!((1, 2), (value.port, value.host));
+}
+
+#[test]
+fn collect_de_retry() {
+    // merged sections and duplicate keys are deserialized the same way after an item is skipped
+    let mut de = Deserializer::from_str("name=app\n[server]\nhost=a\nhost=b\n[server]\nport=http\n")
+        .merge_sections(true)
+        .duplicate_keys(DuplicateKeys::Last);
+    let (value, errors) = de.deserialize_collecting::<TestModel>();
+    assert_eq!(Some(TestModel {
+        name: "app".into(),
+        server: Section {
+            host: "b".into(),
+            port: None,
+            debug: false,
+        },
+    }), value);
+    assert_eq!(vec![Some("port")], errors.iter().map(|e| e.key()).collect::<Vec<_>>());
+}
@@ -0,0 +1,48 @@
+extern crate serde_ini;
+
+use serde_ini::{Parser, Writer, Item, LineEnding};
+
+const TEST_INPUT: &str = "\
+; semicolon
+# hash
+;;doubled
+#
+[server]
+port=80
+";
+
+fn rewrite(input: &str) -> String {
+    let mut writer = Writer::new(Vec::new(), LineEnding::Linefeed);
+    for item in Parser::from_str(input) {
+        writer.write(&item.unwrap().1).unwrap();
+    }
+    String::from_utf8(writer.into_inner()).unwrap()
+}
+
+#[test]
+fn comments_round_trip() {
+    assert_eq!(TEST_INPUT, rewrite(TEST_INPUT));
+}
+
+#[test]
+fn comments_prefix() {
+    let items = Parser::from_str("# hash\n;;doubled\n").map(|item| item.unwrap().1).collect::<Vec<_>>();
+    assert_eq!(vec![
+        Item::Comment { prefix: Some('#'), text: " hash".into() },
+        Item::Comment { prefix: Some(';'), text: ";doubled".into() },
+    ], items);
+}
+
+#[test]
+fn comments_default_prefix() {
+    let comment = Item::Comment { prefix: None, text: " new".into() };
+
+    let mut writer = Writer::new(Vec::new(), LineEnding::Linefeed);
+    writer.write(&comment).unwrap();
+    assert_eq!(b"; new\n", &writer.into_inner()[..]);
+
+    let mut writer = Writer::new(Vec::new(), LineEnding::Linefeed).comment_prefix('#');
+    writer.write(&comment).unwrap();
+    writer.write(&Item::Comment { prefix: Some(';'), text: " kept".into() }).unwrap();
+    assert_eq!(b"# new\n; kept\n", &writer.into_inner()[..]);
+}
@@ -0,0 +1,120 @@
+extern crate serde_ini;
+
+use serde_ini::Document;
+use serde_ini::de::ErrorKind;
+use serde_ini::parse::SyntaxError;
+
+const TEST_INPUT: &str = "; settings\r\nname = app\r\n\r\n[server]\r\n  port = 80 ; http\r\nhost=localhost\r\n\r\n# logging\r\n[log]\r\nlevel =\r\npath=a\r\npath=b";
+
+fn document() -> Document {
+    TEST_INPUT.parse().unwrap()
+}
+
+#[test]
+fn document_round_trip() {
+    assert_eq!(TEST_INPUT, document().to_string());
+    assert_eq!("", Document::new().to_string());
+}
+
+#[test]
+fn document_get() {
+    let doc = document();
+    assert_eq!(Some("app"), doc.get(None, "name"));
+    assert_eq!(Some("80 ; http"), doc.get(Some("server"), "port"));
+    assert_eq!(Some(""), doc.get(Some("log"), "level"));
+    assert_eq!(Some("a"), doc.get(Some("log"), "path"));
+    assert_eq!(None, doc.get(None, "port"));
+    assert_eq!(None, doc.get(Some("missing"), "port"));
+
+    assert_eq!(vec!["server", "log"], doc.sections().collect::<Vec<_>>());
+    assert_eq!(vec!["level", "path", "path"], doc.keys(Some("log")).collect::<Vec<_>>());
+}
+
+#[test]
+fn document_set() {
+    let mut doc = document();
+    assert_eq!(Some("80 ; http".into()), doc.set(Some("server"), "port", "8080").unwrap());
+    assert_eq!(Some("".into()), doc.set(Some("log"), "level", "debug").unwrap());
+    assert_eq!(Some("a".into()), doc.set(Some("log"), "path", "c").unwrap());
+    assert_eq!(TEST_INPUT
+        .replace("80 ; http", "8080")
+        .replace("level =", "level =debug")
+        .replace("path=a\r\npath=b", "path=c"), doc.to_string());
+}
+
+#[test]
+fn document_insert() {
+    let mut doc = document();
+    assert_eq!(None, doc.set(Some("server"), "tls", "on").unwrap());
+    doc.insert(None, "version", "2").unwrap();
+    doc.insert(Some("log"), "path", "d").unwrap();
+    doc.insert(Some("cache"), "size", "10").unwrap();
+    assert!(!doc.insert_section("server").unwrap());
+    assert!(doc.insert_section("empty").unwrap());
+    assert_eq!("\
+; settings\r\nname = app\r\nversion=2\r\n\r\n\
+[server]\r\n  port = 80 ; http\r\nhost=localhost\r\ntls=on\r\n\r\n# logging\r\n\
+[log]\r\nlevel =\r\npath=a\r\npath=b\r\npath=d\r\n\
+[cache]\r\nsize=10\r\n\
+[empty]", doc.to_string());
+
+    let mut doc = Document::new();
+    doc.set(Some("server"), "port", "80").unwrap();
+    doc.set(None, "name", "app").unwrap();
+    assert_eq!("name=app\r\n[server]\r\nport=80\r\n", doc.to_string());
+
+    let mut doc = "; only comments\n\n[server]\n".parse::<Document>().unwrap();
+    doc.set(None, "name", "app").unwrap();
+    doc.set(Some("server"), "port", "80").unwrap();
+    assert_eq!("; only comments\n\nname=app\n[server]\nport=80\n", doc.to_string());
+}
+
+#[test]
+fn document_remove() {
+    let mut doc = document();
+    assert_eq!(Some("a".into()), doc.remove(Some("log"), "path"));
+    assert_eq!(None, doc.remove(Some("log"), "path"));
+    assert!(doc.remove_section("server"));
+    assert!(!doc.remove_section("server"));
+    assert_eq!("; settings\r\nname = app\r\n\r\n[log]\r\nlevel =", doc.to_string());
+}
+
+#[test]
+fn document_repeated_sections() {
+    let mut doc = "[a]\nx=1\n[b]\n[a]\ny=2\n".parse::<Document>().unwrap();
+    assert_eq!(Some("1"), doc.get(Some("a"), "x"));
+    assert_eq!(Some("2"), doc.get(Some("a"), "y"));
+    doc.insert(Some("a"), "z", "3").unwrap();
+    assert_eq!("[a]\nx=1\n[b]\n[a]\ny=2\nz=3\n", doc.to_string());
+    doc.remove_section("a");
+    assert_eq!("[b]\n", doc.to_string());
+}
+
+#[test]
+fn document_errors() {
+    let error = "[server]\nport\n".parse::<Document>().unwrap_err();
+    assert_eq!(Some(2), error.line());
+
+    // edits that would not read back as the same lines are rejected, leaving the document as it was
+    let mut doc = document();
+    for &(section, key, value, expected) in &[
+        (Some("server"), "port", "80\n[admin]", SyntaxError::LineBreak),
+        (Some("server"), "a\nb", "1", SyntaxError::LineBreak),
+        (Some("server"), "a=b", "1", SyntaxError::KeyName),
+        (Some("server"), "[admin]", "1", SyntaxError::KeyName),
+        (None, "; note", "1", SyntaxError::KeyName),
+        (Some("a]b"), "port", "1", SyntaxError::SectionName),
+    ] {
+        let error = doc.set(section, key, value).unwrap_err();
+        match *error.kind() {
+            ErrorKind::Syntax(e) => assert_eq!(expected, e),
+            ref kind => panic!("unexpected {:?}", kind),
+        }
+        assert!(doc.insert(section, key, value).is_err());
+    }
+    assert!(doc.insert_section("a\nb").is_err());
+    assert_eq!(TEST_INPUT, doc.to_string());
+
+    let error = doc.set(Some("server"), "a=b", "1").unwrap_err();
+    assert_eq!("in [server] a=b = \"1\": INI syntax error: key contains '=' or starts with '[', ';' or '#'", error.to_string());
+}
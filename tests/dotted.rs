@@ -0,0 +1,82 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_ini;
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use serde_ini::{Deserializer, Serializer, Writer, LineEnding};
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Default, Debug)]
+struct Pool {
+    max: u32,
+    min: u32,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Default, Debug)]
+struct Db {
+    url: String,
+    pool: Pool,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Default, Debug)]
+struct Server {
+    port: u16,
+    db: Db,
+    labels: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Default, Debug)]
+struct TestModel {
+    server: Server,
+}
+
+const TEST_INPUT: &str = "
+[server]
+db.pool.max=10
+port=8080
+labels.team=infra
+db.url=postgres://localhost
+db.pool.min=1
+";
+
+fn expected() -> TestModel {
+    let mut labels = BTreeMap::new();
+    labels.insert("team".into(), "infra".into());
+
+    TestModel {
+        server: Server {
+            port: 8080,
+            db: Db {
+                url: "postgres://localhost".into(),
+                pool: Pool {
+                    max: 10,
+                    min: 1,
+                },
+            },
+            labels,
+        },
+    }
+}
+
+#[test]
+fn dotted_de() {
+    let mut de = Deserializer::from_str(TEST_INPUT).key_separator('.');
+    assert_eq!(expected(), TestModel::deserialize(&mut de).unwrap());
+
+    // without a separator, dotted keys are just keys
+    let model = serde_ini::from_str::<BTreeMap<String, BTreeMap<String, String>>>(TEST_INPUT).unwrap();
+    assert_eq!("10", model["server"]["db.pool.max"]);
+}
+
+#[test]
+fn dotted_en() {
+    let model = expected();
+
+    let mut data = Vec::new();
+    model.serialize(&mut Serializer::new(Writer::new(&mut data, LineEnding::Linefeed)).key_separator('.')).unwrap();
+    assert_eq!("[server]\nport=8080\ndb.url=postgres://localhost\ndb.pool.max=10\ndb.pool.min=1\nlabels.team=infra\n", String::from_utf8(data.clone()).unwrap());
+
+    let mut de = Deserializer::from_read(&data[..]).key_separator('.');
+    assert_eq!(model, TestModel::deserialize(&mut de).unwrap());
+}
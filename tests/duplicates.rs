@@ -0,0 +1,138 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_ini;
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use serde_ini::{Deserializer, Serializer, Writer, LineEnding, DuplicateKeys};
+use serde_ini::de::ErrorKind;
+
+#[derive(Deserialize, Clone, PartialEq, Default, Debug)]
+struct Section {
+    port: u16,
+    #[serde(default)]
+    hosts: Vec<String>,
+}
+
+#[derive(Deserialize, Clone, PartialEq, Default, Debug)]
+struct TestModel {
+    server: Section,
+}
+
+const TEST_INPUT: &str = "\
+[server]
+port=80
+hosts=a
+port=8080
+hosts=b
+";
+
+fn deserialize(policy: DuplicateKeys) -> Result<TestModel, serde_ini::de::Error> {
+    TestModel::deserialize(&mut Deserializer::from_str(TEST_INPUT).duplicate_keys(policy))
+}
+
+fn serialize<T: Serialize>(value: &T, policy: DuplicateKeys) -> Result<String, serde_ini::ser::Error> {
+    let mut data = Vec::new();
+    value.serialize(&mut Serializer::new(Writer::new(&mut data, LineEnding::Linefeed)).duplicate_keys(policy))?;
+    Ok(String::from_utf8(data).unwrap())
+}
+
+#[test]
+fn duplicates_de() {
+    // a policy picks a single value, while a sequence still collects every occurrence
+    let model = deserialize(DuplicateKeys::First).unwrap();
+    assert_eq!((80, vec!["a".to_owned(), "b".to_owned()]), (model.server.port, model.server.hosts));
+
+    let model = deserialize(DuplicateKeys::Last).unwrap();
+    assert_eq!((8080, vec!["a".to_owned(), "b".to_owned()]), (model.server.port, model.server.hosts));
+
+    let error = deserialize(DuplicateKeys::Error).unwrap_err();
+    match *error.kind() {
+        ErrorKind::DuplicateKey { ref key, first } => assert_eq!(("port", Some(2)), (&key[..], first.map(|p| p.line))),
+        ref kind => panic!("unexpected {:?}", kind),
+    }
+    assert_eq!("in [server] port = \"8080\": duplicate key `port` (first at line 2, column 1) at line 4, column 1", error.to_string());
+
+    // by default repeated keys are only accepted by struct fields as sequences
+    let error = deserialize(DuplicateKeys::Collect).unwrap_err();
+    assert_eq!((Some(4), Some("8080")), (error.line(), error.value()));
+    let model = TestModel::deserialize(&mut Deserializer::from_str("[server]\nport=80\nhosts=a\nhosts=b\n")).unwrap();
+    assert_eq!(vec!["a", "b"], model.server.hosts);
+
+    // errors are attributed to the occurrence that was used
+    let error = TestModel::deserialize(&mut Deserializer::from_str("[server]\nport=80\nport=x\n").duplicate_keys(DuplicateKeys::Last)).unwrap_err();
+    assert_eq!((Some(3), Some("x")), (error.line(), error.value()));
+
+    let map = BTreeMap::<String, BTreeMap<String, String>>::deserialize(
+        &mut Deserializer::from_str(TEST_INPUT).duplicate_keys(DuplicateKeys::First)).unwrap();
+    assert_eq!("80", map["server"]["port"]);
+    let map = BTreeMap::<String, BTreeMap<String, Vec<String>>>::deserialize(
+        &mut Deserializer::from_str(TEST_INPUT).duplicate_keys(DuplicateKeys::First)).unwrap();
+    assert_eq!(vec!["80", "8080"], map["server"]["port"]);
+}
+
+#[test]
+fn duplicates_de_default() {
+    // the values of a map take the last occurrence, as they did before policies existed
+    assert_eq!(DuplicateKeys::Collect, DuplicateKeys::default());
+    let map = serde_ini::from_str::<BTreeMap<String, BTreeMap<String, String>>>("[a]\nx=1\nx=2").unwrap();
+    assert_eq!("2", map["a"]["x"]);
+    let map = serde_ini::from_str::<BTreeMap<String, BTreeMap<String, Vec<String>>>>("[a]\nx=1\nx=2").unwrap();
+    assert_eq!(vec!["1", "2"], map["a"]["x"]);
+}
+
+#[test]
+fn duplicates_case() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Ports {
+        port: Vec<u32>,
+    }
+
+    // keys that differ only in case are the same key when read back
+    let mut section = BTreeMap::new();
+    section.insert("Port", 80);
+    section.insert("port", 8080);
+    let data = serialize(&section, DuplicateKeys::Collect).unwrap();
+    assert_eq!("Port=80\nport=8080\n", data);
+
+    let map = serde_ini::from_str::<BTreeMap<String, Vec<u32>>>(&data).unwrap();
+    assert_eq!(vec![80, 8080], map["Port"]);
+    let map = serde_ini::from_str::<BTreeMap<String, u32>>(&data).unwrap();
+    assert_eq!(8080, map["Port"]);
+    assert_eq!(vec![80, 8080], serde_ini::from_str::<Ports>("port=80\nPort=8080\n").unwrap().port);
+
+    // `Port` is not a field of the struct, and takes `port` with it rather than losing a value
+    match *serde_ini::from_str::<Ports>(&data).unwrap_err().kind() {
+        ErrorKind::MissingField("port") => (),
+        ref kind => panic!("unexpected {:?}", kind),
+    }
+
+    let error = TestModel::deserialize(&mut Deserializer::from_str("[server]\nport=80\nPORT=8080\n").duplicate_keys(DuplicateKeys::Error)).unwrap_err();
+    assert_eq!("in [server] PORT = \"8080\": duplicate key `port` (first at line 2, column 1) at line 3, column 1", error.to_string());
+}
+
+#[test]
+fn duplicates_en() {
+    let mut section = BTreeMap::new();
+    section.insert("Port", "80");
+    section.insert("host", "a");
+    section.insert("port", "8080");
+    let mut model = BTreeMap::new();
+    model.insert("server", section);
+
+    assert_eq!("[server]\nPort=80\nhost=a\nport=8080\n", serialize(&model, DuplicateKeys::Collect).unwrap());
+    assert_eq!("[server]\nPort=80\nhost=a\n", serialize(&model, DuplicateKeys::First).unwrap());
+    assert_eq!("[server]\nhost=a\nport=8080\n", serialize(&model, DuplicateKeys::Last).unwrap());
+    match serialize(&model, DuplicateKeys::Error) {
+        Err(serde_ini::ser::Error::DuplicateKey(ref key)) => assert_eq!("port", key),
+        result => panic!("unexpected {:?}", result),
+    }
+
+    // sections are keys of the top-level map
+    let mut model = BTreeMap::new();
+    model.insert("Server", BTreeMap::new());
+    model.insert("server", BTreeMap::new());
+    model.get_mut("server").unwrap().insert("port", "80");
+    assert_eq!("[server]\nport=80\n", serialize(&model, DuplicateKeys::Last).unwrap());
+}
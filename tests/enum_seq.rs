@@ -3,8 +3,7 @@ extern crate serde_derive;
 extern crate serde;
 extern crate serde_ini;
 
-use serde::Deserialize;
-use serde_ini::{Deserializer, Parser};
+use std::collections::BTreeMap;
 
 #[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
 enum TestModel {
@@ -13,7 +12,7 @@ enum TestModel {
     }
 }
 
-const TEST_INPUT: &'static str = "
+const TEST_INPUT: &str = "
 [Person]
 name=Ana
 
@@ -38,7 +37,6 @@ fn enum_seq_de() {
 }
 
 #[test]
-#[ignore = "not yet implemented"]
 fn enum_seq_en() {
     let model = expected();
 
@@ -46,3 +44,47 @@ fn enum_seq_en() {
 
     assert_eq!(model, serde_ini::from_read::<_, Vec<TestModel>>(&data[..]).unwrap());
 }
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+enum Directive {
+    Include(String),
+    Person {
+        name: String,
+    },
+}
+
+#[test]
+fn enum_seq_newtype() {
+    let model = vec![
+        Directive::Include("a.conf".into()),
+        Directive::Include("b.conf".into()),
+        Directive::Person {
+            name: "Ana".into(),
+        },
+    ];
+
+    let data = serde_ini::to_string(&model).unwrap();
+    assert_eq!("Include=a.conf\r\nInclude=b.conf\r\n[Person]\r\nname=Ana\r\n", data);
+    assert_eq!(model, serde_ini::from_str::<Vec<Directive>>(&data).unwrap());
+
+    let orphan = vec![
+        Directive::Person {
+            name: "Ana".into(),
+        },
+        Directive::Include("a.conf".into()),
+    ];
+    assert!(serde_ini::to_string(&orphan).is_err());
+
+    // within a section, a newtype variant needs a key separator to name its value
+    #[derive(Serialize)]
+    struct Holder {
+        directive: Directive,
+    }
+
+    let mut holder = BTreeMap::new();
+    holder.insert("holder", Holder { directive: Directive::Include("a.conf".into()) });
+    match serde_ini::to_string(&holder) {
+        Err(serde_ini::ser::Error::UnsupportedType(serde_ini::ser::UnsupportedType::NewtypeVariant)) => (),
+        result => panic!("unexpected {:?}", result),
+    }
+}
@@ -0,0 +1,104 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_ini;
+
+use std::error::Error as _;
+use std::collections::BTreeMap;
+use std::{io, num};
+use serde_ini::de::ErrorKind;
+use serde_ini::parse::SyntaxError;
+
+#[derive(Deserialize, Clone, PartialEq, Default, Debug)]
+#[serde(deny_unknown_fields)]
+struct Section {
+    port: u16,
+    debug: bool,
+}
+
+#[derive(Deserialize, Clone, PartialEq, Default, Debug)]
+struct TestModel {
+    server: Section,
+}
+
+struct FailingReader;
+
+impl io::Read for FailingReader {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::PermissionDenied, "denied"))
+    }
+}
+
+struct FailingWriter;
+
+impl io::Write for FailingWriter {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::Error::new(io::ErrorKind::PermissionDenied, "denied"))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn error(input: &str) -> serde_ini::de::Error {
+    serde_ini::from_str::<TestModel>(input).unwrap_err()
+}
+
+#[test]
+fn errors_io() {
+    let e = serde_ini::from_read::<_, TestModel>(FailingReader).unwrap_err();
+    match e.kind() {
+        ErrorKind::Io(e) => assert_eq!(io::ErrorKind::PermissionDenied, e.kind()),
+        kind => panic!("unexpected {:?}", kind),
+    }
+    let source = e.source().unwrap().downcast_ref::<io::Error>().unwrap();
+    assert_eq!(io::ErrorKind::PermissionDenied, source.kind());
+
+    let mut model = BTreeMap::new();
+    model.insert("server", BTreeMap::<&str, &str>::new());
+    let e = serde_ini::to_writer(FailingWriter, &model).unwrap_err();
+    match e {
+        serde_ini::ser::Error::Io(ref e) => assert_eq!(io::ErrorKind::PermissionDenied, e.kind()),
+        ref e => panic!("unexpected {:?}", e),
+    }
+    assert_eq!("I/O error: denied", e.to_string());
+    assert!(e.source().unwrap().downcast_ref::<io::Error>().is_some());
+}
+
+#[test]
+fn errors_kinds() {
+    match *error("[server\n").kind() {
+        ErrorKind::Syntax(SyntaxError::SectionNotClosed) => (),
+        ref kind => panic!("unexpected {:?}", kind),
+    }
+
+    let e = error("[server]\nport=x\ndebug=no\n");
+    match e.kind() {
+        ErrorKind::InvalidValue { expected, found } => assert_eq!(("u16", "string \"x\""), (&expected[..], &found[..])),
+        kind => panic!("unexpected {:?}", kind),
+    }
+    assert!(e.source().unwrap().downcast_ref::<num::ParseIntError>().is_some());
+
+    let e = error("[server]\nport=1\ndebug=maybe\n");
+    match e.kind() {
+        ErrorKind::InvalidValue { expected, .. } => assert_eq!("a boolean", expected),
+        kind => panic!("unexpected {:?}", kind),
+    }
+    assert!(e.source().is_none());
+
+    match *error("[server]\nport=1\n").kind() {
+        ErrorKind::MissingField("debug") => (),
+        ref kind => panic!("unexpected {:?}", kind),
+    }
+
+    match error("[server]\nport=1\ndebug=no\nhost=h\n").kind() {
+        ErrorKind::UnknownField { field, expected, .. } => assert_eq!(("host", &["port", "debug"][..]), (&field[..], *expected)),
+        kind => panic!("unexpected {:?}", kind),
+    }
+
+    match error("[server]\nport=1\ndebug=no\nport=2\n").kind() {
+        ErrorKind::DuplicateKey { key, first } => assert_eq!(("port", Some(2)), (&key[..], first.map(|p| p.line))),
+        kind => panic!("unexpected {:?}", kind),
+    }
+}
@@ -0,0 +1,160 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_ini;
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use serde_ini::{Deserializer, Serializer, Writer, LineEnding};
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Default, Debug)]
+struct Common {
+    owner: String,
+    region: String,
+    tags: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Default, Debug)]
+struct Tls {
+    cert: String,
+    #[serde(flatten)]
+    common: Common,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Default, Debug)]
+struct Server {
+    host: String,
+    #[serde(flatten)]
+    common: Common,
+    tls: Tls,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Default, Debug)]
+struct TestModel {
+    name: String,
+    #[serde(flatten)]
+    common: Common,
+    server: Server,
+}
+
+const TEST_INPUT: &str = "
+owner=ops
+name=main
+tags=a
+region=eu
+tags=b
+
+[server]
+region=us
+host=localhost
+owner=web
+tags=web
+tls.tags=x
+tags=api
+tls.cert=server.pem
+tls.owner=sec
+tls.region=eu
+tls.tags=y
+";
+
+fn expected() -> TestModel {
+    TestModel {
+        name: "main".into(),
+        common: Common {
+            owner: "ops".into(),
+            region: "eu".into(),
+            tags: vec!["a".into(), "b".into()],
+        },
+        server: Server {
+            host: "localhost".into(),
+            common: Common {
+                owner: "web".into(),
+                region: "us".into(),
+                tags: vec!["web".into(), "api".into()],
+            },
+            tls: Tls {
+                cert: "server.pem".into(),
+                common: Common {
+                    owner: "sec".into(),
+                    region: "eu".into(),
+                    tags: vec!["x".into(), "y".into()],
+                },
+            },
+        },
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Default, Debug)]
+struct Extra {
+    name: String,
+    #[serde(flatten)]
+    rest: BTreeMap<String, String>,
+}
+
+#[test]
+fn flatten_de() {
+    let mut de = Deserializer::from_str(TEST_INPUT).key_separator('.');
+    assert_eq!(expected(), TestModel::deserialize(&mut de).unwrap());
+
+    // a catch-all map collects every key without a field of its own
+    let extra: Extra = serde_ini::from_str("name=n\nport=8080\nhost=h\n").unwrap();
+    assert_eq!("8080", extra.rest["port"]);
+    assert_eq!("h", extra.rest["host"]);
+}
+
+#[test]
+fn flatten_en() {
+    let model = expected();
+
+    let mut data = Vec::new();
+    model.serialize(&mut Serializer::new(Writer::new(&mut data, LineEnding::Linefeed)).key_separator('.')).unwrap();
+    assert_eq!("name=main\nowner=ops\nregion=eu\ntags=a\ntags=b\n\
+        [server]\nhost=localhost\nowner=web\nregion=us\ntags=web\ntags=api\n\
+        tls.cert=server.pem\ntls.owner=sec\ntls.region=eu\ntls.tags=x\ntls.tags=y\n", String::from_utf8(data.clone()).unwrap());
+
+    let mut de = Deserializer::from_read(&data[..]).key_separator('.');
+    assert_eq!(model, TestModel::deserialize(&mut de).unwrap());
+}
+
+#[test]
+fn flatten_limits() {
+    #[derive(Deserialize, Clone, PartialEq, Debug)]
+    struct Limits {
+        version: String,
+        retries: u32,
+        verbose: bool,
+    }
+
+    #[derive(Deserialize, Clone, PartialEq, Debug)]
+    struct Service {
+        host: String,
+        #[serde(flatten)]
+        limits: Limits,
+    }
+
+    #[derive(Deserialize, Clone, PartialEq, Debug)]
+    struct Model {
+        #[serde(flatten)]
+        limits: Limits,
+        service: Service,
+    }
+
+    const INPUT: &str = "version=v1\nretries=1\nverbose=no\n[service]\nhost=h\nversion=v2\nretries=3\nverbose=yes\n";
+
+    // flattened values are buffered without their field types, so they are strings by default
+    let e = serde_ini::from_str::<Model>(INPUT).unwrap_err();
+    assert_eq!("in [service] retries = \"3\": invalid value: string \"3\", expected u32 at line 7, column 1", e.to_string());
+
+    let mut de = Deserializer::from_str(INPUT).infer_types(true);
+    let model = Model::deserialize(&mut de).unwrap();
+    assert_eq!(Limits { version: "v1".into(), retries: 1, verbose: false }, model.limits);
+    assert_eq!(Limits { version: "v2".into(), retries: 3, verbose: true }, model.service.limits);
+
+    // with inference a string field cannot hold a value that looks like a number or a bool
+    let mut de = Deserializer::from_str("version=1.0\nretries=1\nverbose=no\n[service]\nhost=h\nversion=v2\nretries=3\nverbose=yes\n").infer_types(true);
+    let e = Model::deserialize(&mut de).unwrap_err();
+    assert_eq!((None, Some("version"), Some(1)), (e.section(), e.key(), e.line()));
+    let mut de = Deserializer::from_str("version=v1\nretries=1\nverbose=no\n[service]\nhost=h\nversion=no\nretries=3\nverbose=yes\n").infer_types(true);
+    let e = Model::deserialize(&mut de).unwrap_err();
+    assert_eq!("in [service] version = \"no\": invalid value: boolean `false`, expected a string at line 6, column 1", e.to_string());
+}
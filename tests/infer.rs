@@ -0,0 +1,127 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_ini;
+
+use std::collections::BTreeMap;
+use serde::Deserialize;
+use serde_ini::Deserializer;
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(untagged)]
+enum Port {
+    Num(u16),
+    Name(String),
+}
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(untagged)]
+enum Dynamic {
+    Int(i64),
+    Float(f64),
+    Bool(bool),
+    Str(String),
+    List(Vec<Dynamic>),
+}
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+struct Listen {
+    port: Port,
+    fallback: Port,
+}
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+struct TestModel {
+    listen: Listen,
+    values: BTreeMap<String, Dynamic>,
+}
+
+const TEST_INPUT: &str = "
+[listen]
+port=8080
+fallback=http
+
+[values]
+count=-3
+ratio=0.5
+enabled=on
+name=007x
+nan=NaN
+peers=1
+peers=two
+";
+
+#[test]
+fn infer_de() {
+    let mut values = BTreeMap::new();
+    values.insert("count".into(), Dynamic::Int(-3));
+    values.insert("ratio".into(), Dynamic::Float(0.5));
+    values.insert("enabled".into(), Dynamic::Bool(true));
+    values.insert("name".into(), Dynamic::Str("007x".into()));
+    values.insert("nan".into(), Dynamic::Str("NaN".into()));
+    values.insert("peers".into(), Dynamic::List(vec![Dynamic::Int(1), Dynamic::Str("two".into())]));
+
+    let mut de = Deserializer::from_str(TEST_INPUT).infer_types(true);
+    assert_eq!(TestModel {
+        listen: Listen {
+            port: Port::Num(8080),
+            fallback: Port::Name("http".into()),
+        },
+        values,
+    }, TestModel::deserialize(&mut de).unwrap());
+}
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum Backend {
+    File { path: String, size: u32, sync: bool },
+    Null,
+}
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+struct Limits {
+    timeout: u32,
+    verbose: bool,
+}
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+struct Server {
+    host: String,
+    #[serde(flatten)]
+    limits: Limits,
+}
+
+#[derive(Deserialize, Clone, PartialEq, Debug)]
+struct Fields {
+    backend: Backend,
+    server: Server,
+}
+
+#[test]
+fn infer_fields() {
+    // the fields of internally tagged enums and flattened structs are buffered without a type hint
+    let input = "[backend]\nkind=file\npath=/var/db\nsize=3\nsync=yes\n[server]\nhost=localhost\ntimeout=5\nverbose=no\n";
+    let mut de = Deserializer::from_str(input).infer_types(true);
+    assert_eq!(Fields {
+        backend: Backend::File {
+            path: "/var/db".into(),
+            size: 3,
+            sync: true,
+        },
+        server: Server {
+            host: "localhost".into(),
+            limits: Limits {
+                timeout: 5,
+                verbose: false,
+            },
+        },
+    }, Fields::deserialize(&mut de).unwrap());
+}
+
+#[test]
+fn infer_disabled() {
+    let model: TestModel = serde_ini::from_str(TEST_INPUT).unwrap();
+    assert_eq!(Port::Name("8080".into()), model.listen.port);
+    assert_eq!(Dynamic::Str("-3".into()), model.values["count"]);
+    assert_eq!(Dynamic::Str("on".into()), model.values["enabled"]);
+}
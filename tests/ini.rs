@@ -0,0 +1,99 @@
+extern crate serde_ini;
+
+use serde_ini::{Ini, Writer, LineEnding};
+use serde_ini::de::ErrorKind;
+
+const TEST_INPUT: &str = "\
+name=app
+[server]
+port=8080
+ratio = 0.5
+debug=yes
+allow=a
+allow=b
+[log]
+level=x
+";
+
+fn ini() -> Ini {
+    TEST_INPUT.parse().unwrap()
+}
+
+#[test]
+fn ini_get() {
+    let ini = ini();
+    assert_eq!("app", ini.get(None, "name").unwrap());
+    assert_eq!("8080", ini.get(Some("server"), "port").unwrap());
+    assert_eq!(8080, ini.get_int(Some("server"), "port").unwrap());
+    assert_eq!(0.5, ini.get_float(Some("server"), "ratio").unwrap());
+    assert!(ini.get_bool(Some("server"), "debug").unwrap());
+    assert_eq!(vec!["a", "b"], ini.get_as::<Vec<String>>(Some("server"), "allow").unwrap());
+    assert_eq!(3, ini.get_or(Some("server"), "retries", 3).unwrap());
+    assert_eq!(8080u16, ini.get_or(Some("server"), "port", 80).unwrap());
+
+    assert_eq!(vec!["server", "log"], ini.sections().collect::<Vec<_>>());
+    assert_eq!(vec!["port", "ratio", "debug", "allow"], ini.keys(Some("server")).collect::<Vec<_>>());
+    assert!(ini.has_section("log"));
+    assert!(!ini.has_section("cache"));
+}
+
+#[test]
+fn ini_errors() {
+    let ini = ini();
+    let e = ini.get_int(Some("log"), "level").unwrap_err();
+    assert_eq!("in [log] level = \"x\": invalid value: string \"x\", expected i64 (invalid digit found in string) at line 9, column 1", e.to_string());
+    assert!(ini.get_or(Some("log"), "level", 0).is_err());
+
+    let e = ini.get_int(Some("server"), "retries").unwrap_err();
+    match *e.kind() {
+        ErrorKind::MissingKey => (),
+        ref kind => panic!("unexpected {:?}", kind),
+    }
+    assert_eq!("in [server] retries: missing key at line 2, column 1", e.to_string());
+    assert_eq!(None, ini.get(Some("cache"), "size").unwrap_err().line());
+    assert_eq!("in version: missing key", ini.get(None, "version").unwrap_err().to_string());
+
+    let e = ini.get_as::<String>(Some("server"), "allow").unwrap_err();
+    assert_eq!((Some(7), "duplicate key `allow` (first at line 6, column 1)".to_string()), (e.line(), e.kind().to_string()));
+
+    assert_eq!(Some(2), "[server]\nport\n".parse::<Ini>().unwrap_err().line());
+}
+
+#[test]
+fn ini_empty_section() {
+    let ini: Ini = "name=app\n[]\nname=empty\n".parse().unwrap();
+    assert_eq!("app", ini.get(None, "name").unwrap());
+    assert_eq!("empty", ini.get(Some(""), "name").unwrap());
+    assert_eq!(vec![""], ini.sections().collect::<Vec<_>>());
+    assert!(ini.has_section(""));
+
+    let mut writer = Writer::new(Vec::new(), LineEnding::Linefeed);
+    ini.write(&mut writer).unwrap();
+    assert_eq!("name=app\n[]\nname=empty\n", String::from_utf8(writer.into_inner()).unwrap());
+}
+
+#[test]
+fn ini_set() {
+    let mut ini = ini();
+    ini.set(Some("server"), "allow", "c");
+    ini.set(Some("server"), "port", "80");
+    ini.set(Some("cache"), "size", "10");
+    ini.set(None, "version", "2");
+    assert_eq!(80, ini.get_int(Some("server"), "port").unwrap());
+
+    let mut writer = Writer::new(Vec::new(), LineEnding::Linefeed);
+    ini.write(&mut writer).unwrap();
+    assert_eq!("\
+name=app
+version=2
+[server]
+port=80
+ratio=0.5
+debug=yes
+allow=c
+[log]
+level=x
+[cache]
+size=10
+", String::from_utf8(writer.into_inner()).unwrap());
+}
@@ -0,0 +1,94 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_ini;
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use serde_ini::{Deserializer, Serializer, Writer, LineEnding};
+
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+enum LogLevel {
+    Debug,
+    Warn,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Default, Debug)]
+struct Keys {
+    levels: BTreeMap<LogLevel, String>,
+    flags: BTreeMap<bool, String>,
+    drives: BTreeMap<char, String>,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Default, Debug)]
+struct TestModel {
+    ports: BTreeMap<u16, String>,
+    offsets: BTreeMap<i32, u32>,
+    keys: Keys,
+}
+
+const TEST_INPUT: &str = "\
+[ports]
+80=http
+443=https
+[offsets]
+-1=5
+2=10
+[keys]
+levels.Debug=debug.log
+levels.Warn=warn.log
+flags.true=on
+flags.false=off
+drives.C=system
+";
+
+fn expected() -> TestModel {
+    let mut model = TestModel::default();
+    model.ports.insert(80, "http".into());
+    model.ports.insert(443, "https".into());
+    model.offsets.insert(-1, 5);
+    model.offsets.insert(2, 10);
+    model.keys.levels.insert(LogLevel::Debug, "debug.log".into());
+    model.keys.levels.insert(LogLevel::Warn, "warn.log".into());
+    model.keys.flags.insert(true, "on".into());
+    model.keys.flags.insert(false, "off".into());
+    model.keys.drives.insert('C', "system".into());
+    model
+}
+
+#[test]
+fn keys_de() {
+    let mut de = Deserializer::from_str(TEST_INPUT).key_separator('.');
+    assert_eq!(expected(), TestModel::deserialize(&mut de).unwrap());
+
+    // sections can be keyed by number too
+    let sections: BTreeMap<u16, BTreeMap<String, String>> = serde_ini::from_str("[80]\nname=http\n").unwrap();
+    assert_eq!("http", sections[&80]["name"]);
+
+    assert!(serde_ini::from_str::<BTreeMap<String, BTreeMap<u16, String>>>("[ports]\nhttp=80\n").is_err());
+}
+
+#[test]
+fn keys_en() {
+    let model = expected();
+
+    let mut data = Vec::new();
+    model.serialize(&mut Serializer::new(Writer::new(&mut data, LineEnding::Linefeed)).key_separator('.')).unwrap();
+    assert_eq!("\
+[ports]
+80=http
+443=https
+[offsets]
+-1=5
+2=10
+[keys]
+levels.Debug=debug.log
+levels.Warn=warn.log
+flags.false=off
+flags.true=on
+drives.C=system
+", String::from_utf8(data.clone()).unwrap());
+
+    let mut de = Deserializer::from_read(&data[..]).key_separator('.');
+    assert_eq!(model, TestModel::deserialize(&mut de).unwrap());
+}
@@ -0,0 +1,60 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_ini;
+
+use serde::{Deserialize, Serialize};
+use serde_ini::{Deserializer, Serializer, Writer, LineEnding};
+use serde_ini::parse::split_list;
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Default, Debug)]
+struct Section {
+    hosts: Vec<String>,
+    ports: Vec<u16>,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Default, Debug)]
+struct TestModel {
+    server: Section,
+}
+
+const TEST_INPUT: &str = "
+[server]
+hosts = a.example, \"b, example\" , c\\,example
+ports = 80,443
+";
+
+fn expected() -> TestModel {
+    TestModel {
+        server: Section {
+            hosts: vec!["a.example".into(), "b, example".into(), "c,example".into()],
+            ports: vec![80, 443],
+        },
+    }
+}
+
+#[test]
+fn list_split() {
+    assert_eq!(vec!["a", "b", "c"], split_list("a;b;c;", ';'));
+    assert_eq!(vec!["a", "", "b"], split_list("a,,b", ','));
+    assert_eq!(vec![" x ", "C:\\dir"], split_list("\" x \", C:\\dir", ','));
+    assert!(split_list("", ',').is_empty());
+}
+
+#[test]
+fn list_de() {
+    let mut de = Deserializer::from_str(TEST_INPUT).list_separator(',');
+    assert_eq!(expected(), TestModel::deserialize(&mut de).unwrap());
+}
+
+#[test]
+fn list_en() {
+    let model = expected();
+
+    let mut data = Vec::new();
+    model.serialize(&mut Serializer::new(Writer::new(&mut data, LineEnding::Linefeed)).list_separator(',')).unwrap();
+    assert_eq!("[server]\nhosts=a.example,\"b, example\",\"c,example\"\nports=80,443\n", String::from_utf8(data.clone()).unwrap());
+
+    let mut de = Deserializer::from_read(&data[..]).list_separator(',');
+    assert_eq!(model, TestModel::deserialize(&mut de).unwrap());
+}
@@ -0,0 +1,80 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+#[macro_use]
+extern crate serde_ini;
+
+use serde_ini::Value;
+
+#[derive(Deserialize, Clone, PartialEq, Default, Debug)]
+struct Server {
+    port: u16,
+    debug: bool,
+    allow: Vec<String>,
+}
+
+#[derive(Deserialize, Clone, PartialEq, Default, Debug)]
+struct TestModel {
+    name: String,
+    server: Server,
+}
+
+#[test]
+fn macro_value() {
+    let port = 8080u16;
+    let value = ini!{
+        "name" => "app",
+        [server] {
+            "port" => port,
+            "debug" => true,
+            "allow" => vec!["a", "b"],
+        },
+        ["server.tls"] {}
+        [empty] {
+            "x" => 1.5
+        }
+    };
+    assert_eq!(Value {
+        global: vec![("name".into(), "app".into())],
+        sections: vec![
+            ("server".into(), vec![
+                ("port".into(), "8080".into()),
+                ("debug".into(), "true".into()),
+                ("allow".into(), "a".into()),
+                ("allow".into(), "b".into()),
+            ]),
+            ("server.tls".into(), vec![]),
+            ("empty".into(), vec![("x".into(), "1.5".into())]),
+        ],
+    }, value);
+
+    assert_eq!(Value::new(), ini!{});
+    assert_eq!(vec![("a".to_string(), "1".to_string())], ini!{ "a" => 1 }.global);
+}
+
+#[test]
+fn macro_fixture() {
+    let model: TestModel = serde_ini::from_value(ini!{
+        "name" => "app",
+        [server] {
+            "port" => 80,
+            "debug" => "yes",
+            "allow" => ["x"],
+        }
+    }).unwrap();
+    assert_eq!(TestModel {
+        name: "app".into(),
+        server: Server { port: 80, debug: true, allow: vec!["x".into()] },
+    }, model);
+}
+
+#[test]
+#[should_panic(expected = "not a single key")]
+fn macro_nested() {
+    #[derive(Serialize)]
+    struct Nested {
+        x: u8,
+    }
+
+    ini!{ "nested" => Nested { x: 1 } };
+}
@@ -0,0 +1,80 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_ini;
+
+use std::collections::BTreeMap;
+use serde::Deserialize;
+use serde_ini::{Deserializer, DuplicateKeys};
+
+#[derive(Deserialize, Clone, PartialEq, Default, Debug)]
+struct Paths {
+    data: String,
+    #[serde(default)]
+    logs: Option<String>,
+}
+
+#[derive(Deserialize, Clone, PartialEq, Default, Debug)]
+struct Settings {
+    debug: bool,
+}
+
+#[derive(Deserialize, Clone, PartialEq, Default, Debug)]
+struct TestModel {
+    paths: Paths,
+    settings: Settings,
+}
+
+const TEST_INPUT: &str = "\
+[paths]
+data=/var/lib/app
+[settings]
+debug=false
+[paths]
+logs=/var/log/app
+data=/srv/app
+";
+
+#[test]
+fn merge_de() {
+    let mut de = Deserializer::from_str(TEST_INPUT).merge_sections(true).duplicate_keys(DuplicateKeys::Last);
+    assert_eq!(TestModel {
+        paths: Paths {
+            data: "/srv/app".into(),
+            logs: Some("/var/log/app".into()),
+        },
+        settings: Settings { debug: false },
+    }, TestModel::deserialize(&mut de).unwrap());
+
+    let mut de = Deserializer::from_str(TEST_INPUT).merge_sections(true).duplicate_keys(DuplicateKeys::First);
+    assert_eq!("/var/lib/app", TestModel::deserialize(&mut de).unwrap().paths.data);
+
+    // conflicting keys refer to both sections
+    let mut de = Deserializer::from_str(TEST_INPUT).merge_sections(true).duplicate_keys(DuplicateKeys::Error);
+    let error = TestModel::deserialize(&mut de).unwrap_err();
+    assert_eq!("in [paths] data = \"/srv/app\": duplicate key `data` (first at line 2, column 1) at line 7, column 1", error.to_string());
+
+    // each header is otherwise a key of its own
+    assert!(serde_ini::from_str::<TestModel>(TEST_INPUT).is_err());
+}
+
+#[test]
+fn merge_de_seq() {
+    #[derive(Deserialize, Clone, PartialEq, Debug)]
+    enum Entry {
+        Person { name: String },
+        Settings { debug: bool },
+    }
+
+    // every header of a top-level sequence is an element of its own
+    let mut de = Deserializer::from_str("[Person]\nname=Ana\n[Settings]\ndebug=true\n[Person]\nname=Box\n").merge_sections(true);
+    assert_eq!(vec![
+        Entry::Person { name: "Ana".into() },
+        Entry::Settings { debug: true },
+        Entry::Person { name: "Box".into() },
+    ], Vec::<Entry>::deserialize(&mut de).unwrap());
+
+    let mut de = Deserializer::from_str("[peer]\nkey=a\n[peer]\nkey=b\n").merge_sections(true);
+    let peers = Vec::<(String, BTreeMap<String, String>)>::deserialize(&mut de).unwrap();
+    assert_eq!(vec!["a", "b"], peers.iter().map(|(_, peer)| &peer["key"][..]).collect::<Vec<_>>());
+}
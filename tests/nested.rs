@@ -0,0 +1,90 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_ini;
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use serde_ini::{Deserializer, Serializer, Writer, LineEnding};
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Default, Debug)]
+struct Tls {
+    cert: String,
+    key: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Default, Debug)]
+struct Server {
+    port: u16,
+    tls: Tls,
+    host: String,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Default, Debug)]
+struct TestModel {
+    name: String,
+    server: Server,
+    #[serde(default)]
+    env: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+const TEST_INPUT: &str = "
+name=test
+
+[server.tls]
+cert=a.pem
+
+[env.prod]
+level=warn
+
+[server]
+port=443
+host=example.com
+
+[server.tls]
+key=a.key
+";
+
+fn expected() -> TestModel {
+    let mut prod = BTreeMap::new();
+    prod.insert("level".into(), "warn".into());
+    let mut env = BTreeMap::new();
+    env.insert("prod".into(), prod);
+
+    TestModel {
+        name: "test".into(),
+        server: Server {
+            port: 443,
+            tls: Tls {
+                cert: "a.pem".into(),
+                key: "a.key".into(),
+            },
+            host: "example.com".into(),
+        },
+        env,
+    }
+}
+
+#[test]
+fn nested_de() {
+    let mut de = Deserializer::from_str(TEST_INPUT).section_separator('.');
+    assert_eq!(expected(), TestModel::deserialize(&mut de).unwrap());
+
+    // without a separator, dotted names are just section names
+    let model = serde_ini::from_str::<BTreeMap<String, BTreeMap<String, String>>>("[a.b]\nc=d").unwrap();
+    assert_eq!("d", model["a.b"]["c"]);
+}
+
+#[test]
+fn nested_en() {
+    let model = expected();
+
+    let mut data = Vec::new();
+    model.serialize(&mut Serializer::new(Writer::new(&mut data, LineEnding::Linefeed)).section_separator('/')).unwrap();
+    assert_eq!("name=test\n[server]\nport=443\nhost=example.com\n[server/tls]\ncert=a.pem\nkey=a.key\n[env]\n[env/prod]\nlevel=warn\n", String::from_utf8(data.clone()).unwrap());
+
+    let mut de = Deserializer::from_read(&data[..]).section_separator('/');
+    assert_eq!(model, TestModel::deserialize(&mut de).unwrap());
+
+    assert!(serde_ini::to_string(&model).is_err());
+}
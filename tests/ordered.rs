@@ -0,0 +1,76 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_ini;
+
+use serde::{Deserialize, Serialize};
+use serde_ini::{Deserializer, Serializer, Writer, LineEnding};
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Default, Debug)]
+struct Rule {
+    action: String,
+    port: u16,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Default, Debug)]
+struct NamedRule {
+    name: String,
+    #[serde(flatten)]
+    rule: Rule,
+}
+
+const TEST_INPUT: &str = "\
+[zeta]
+action=allow
+port=80
+[alpha]
+action=deny
+port=22
+[zeta]
+action=log
+port=443
+";
+
+fn expected() -> Vec<(String, Rule)> {
+    vec![
+        ("zeta".into(), Rule { action: "allow".into(), port: 80 }),
+        ("alpha".into(), Rule { action: "deny".into(), port: 22 }),
+        ("zeta".into(), Rule { action: "log".into(), port: 443 }),
+    ]
+}
+
+#[test]
+fn ordered_de() {
+    assert_eq!(expected(), serde_ini::from_str::<Vec<(String, Rule)>>(TEST_INPUT).unwrap());
+
+    let mut de = Deserializer::from_str(TEST_INPUT).section_name_key("name").infer_types(true);
+    let named = Vec::<NamedRule>::deserialize(&mut de).unwrap();
+    assert_eq!(expected(), named.into_iter().map(|named| (named.name, named.rule)).collect::<Vec<_>>());
+
+    // top-level keys are pairs too
+    let pairs: Vec<(String, u32)> = serde_ini::from_str("b=1\na=2\nb=3\n").unwrap();
+    assert_eq!(vec![("b".into(), 1), ("a".into(), 2), ("b".into(), 3)], pairs);
+}
+
+#[test]
+fn ordered_en() {
+    let model = expected();
+    let data = serde_ini::to_vec(&model).unwrap();
+    assert_eq!(TEST_INPUT.replace('\n', "\r\n"), String::from_utf8(data.clone()).unwrap());
+    assert_eq!(model, serde_ini::from_read::<_, Vec<(String, Rule)>>(&data[..]).unwrap());
+
+    let named: Vec<_> = model.into_iter().map(|(name, rule)| NamedRule { name, rule }).collect();
+    let mut data = Vec::new();
+    named.serialize(&mut Serializer::new(Writer::new(&mut data, LineEnding::Linefeed)).section_name_key("name")).unwrap();
+    assert_eq!(TEST_INPUT, String::from_utf8(data).unwrap());
+
+    // the name must come first
+    #[derive(Serialize)]
+    struct Unnamed {
+        port: u16,
+        name: String,
+    }
+    let mut data = Vec::new();
+    let unnamed = vec![Unnamed { port: 1, name: "a".into() }];
+    assert!(unnamed.serialize(&mut Serializer::new(Writer::new(&mut data, LineEnding::Linefeed)).section_name_key("name")).is_err());
+}
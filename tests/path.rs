@@ -0,0 +1,59 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_ini;
+
+use std::collections::BTreeMap;
+use serde::Deserialize;
+use serde_ini::Deserializer;
+
+#[derive(Deserialize, Clone, PartialEq, Default, Debug)]
+struct Pool {
+    max: u32,
+}
+
+#[derive(Deserialize, Clone, PartialEq, Default, Debug)]
+struct Db {
+    pool: Pool,
+    ports: Vec<u16>,
+}
+
+#[derive(Deserialize, Clone, PartialEq, Default, Debug)]
+struct TestModel {
+    count: u32,
+    db: Db,
+}
+
+fn error(input: &str) -> serde_ini::de::Error {
+    let mut de = Deserializer::from_str(input).key_separator('.');
+    TestModel::deserialize(&mut de).unwrap_err()
+}
+
+#[test]
+fn path_values() {
+    let e = serde_ini::from_str::<BTreeMap<String, BTreeMap<String, u32>>>("[map1]\nkey1=1\nkey2=abc\n").unwrap_err();
+    assert_eq!((Some("map1"), Some("key2"), Some("abc")), (e.section(), e.key(), e.value()));
+    assert_eq!("in [map1] key2 = \"abc\": invalid value: string \"abc\", expected u32 (invalid digit found in string) at line 3, column 1", e.to_string());
+
+    let e = error("count=-1\n[db]\nports=1\npool.max=2\n");
+    assert_eq!((None, Some("count"), Some("-1")), (e.section(), e.key(), e.value()));
+    assert_eq!("in count = \"-1\": invalid value: string \"-1\", expected u32 (invalid digit found in string) at line 1, column 1", e.to_string());
+
+    let e = error("count=1\n[db]\nports=1\nports=x\npool.max=2\n");
+    assert_eq!((Some("db"), Some("ports"), Some("x")), (e.section(), e.key(), e.value()));
+    assert_eq!(Some(4), e.line());
+
+    let e = error("count=1\n[db]\nports=1\npool.max=\n");
+    assert_eq!((Some("db"), Some("pool.max"), Some("")), (e.section(), e.key(), e.value()));
+}
+
+#[test]
+fn path_missing() {
+    let e = error("count=1\n[db]\npool.min=1\nports=1\n");
+    assert_eq!((Some("db"), Some("pool"), None), (e.section(), e.key(), e.value()));
+    assert_eq!("in [db] pool: missing field `max` at line 3, column 1", e.to_string());
+
+    let e = error("count=1\n[db]\npool.max=1\n");
+    assert_eq!((Some("db"), None), (e.section(), e.key()));
+    assert_eq!("in [db]: missing field `ports` at line 2, column 1", e.to_string());
+}
@@ -0,0 +1,54 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_ini;
+
+use serde::Deserialize;
+use serde_ini::{Deserializer, Parser, Item, Position};
+
+#[derive(Deserialize, Clone, PartialEq, Default, Debug)]
+struct Section {
+    port: u16,
+    host: String,
+}
+
+#[derive(Deserialize, Clone, PartialEq, Default, Debug)]
+struct TestModel {
+    name: String,
+    server: Section,
+}
+
+#[test]
+fn position_items() {
+    let items = Parser::from_str("; comment\n\n[server]\n  port = 80\n").collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(vec![
+        (Position { line: 1, column: 1 }, Item::Comment { prefix: Some(';'), text: " comment".into() }),
+        (Position { line: 2, column: 1 }, Item::Empty),
+        (Position { line: 3, column: 1 }, Item::Section { name: "server".into() }),
+        (Position { line: 4, column: 3 }, Item::Value { key: "port".into(), value: "80".into() }),
+    ], items);
+
+    let error = Parser::from_str("[server]\nport\n").nth(1).unwrap().unwrap_err();
+    assert_eq!(Some(Position { line: 2, column: 1 }), error.position());
+}
+
+#[test]
+fn position_errors() {
+    let error = serde_ini::from_str::<TestModel>("name=a\n[server]\nhost=h\n  port=abc\n").unwrap_err();
+    assert_eq!((Some(4), Some(3)), (error.line(), error.column()));
+    assert_eq!("in [server] port = \"abc\": invalid value: string \"abc\", expected u16 (invalid digit found in string) at line 4, column 3", error.to_string());
+
+    // errors within a section without a position of their own refer to its header
+    let error = serde_ini::from_str::<TestModel>("name=a\n\n[server]\nhost=h\n").unwrap_err();
+    assert_eq!(Some(3), error.line());
+
+    // syntax errors
+    let mut de = Deserializer::from_str("name=a\n[server\n").file_name("app.ini");
+    let error = TestModel::deserialize(&mut de).unwrap_err();
+    assert_eq!(Some("app.ini"), error.file());
+    assert_eq!("INI syntax error: section missing ']' at line 2, column 1 of app.ini", error.to_string());
+
+    // missing top-level fields have no position
+    let error = serde_ini::from_str::<TestModel>("[server]\nhost=h\nport=1\n").unwrap_err();
+    assert_eq!(None, error.position());
+}
@@ -0,0 +1,98 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_ini;
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Default, Debug)]
+struct Interface {
+    #[serde(rename = "Address", default)]
+    address: Vec<String>,
+    #[serde(rename = "ListenPort")]
+    listen_port: u16,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Default, Debug)]
+struct TestModel {
+    #[serde(rename = "Include", default)]
+    include: Vec<String>,
+    #[serde(rename = "Interface")]
+    interface: Interface,
+}
+
+const TEST_INPUT: &str = "
+Include=a.conf
+Include=b.conf
+
+[Interface]
+Address=10.0.0.1/24
+ListenPort=51820
+Address=fd00::1/64
+";
+
+fn expected() -> TestModel {
+    TestModel {
+        include: vec!["a.conf".into(), "b.conf".into()],
+        interface: Interface {
+            address: vec!["10.0.0.1/24".into(), "fd00::1/64".into()],
+            listen_port: 51820,
+        },
+    }
+}
+
+#[test]
+fn seq_de() {
+    assert_eq!(expected(), serde_ini::from_str::<TestModel>(TEST_INPUT).unwrap());
+}
+
+#[test]
+fn seq_de_empty() {
+    let model = serde_ini::from_str::<TestModel>("[Interface]\nListenPort=1").unwrap();
+    assert!(model.include.is_empty());
+    assert!(model.interface.address.is_empty());
+}
+
+#[test]
+fn seq_en() {
+    let model = expected();
+
+    let data = serde_ini::to_string(&model).unwrap();
+    assert_eq!("Include=a.conf\r\nInclude=b.conf\r\n[Interface]\r\nAddress=10.0.0.1/24\r\nAddress=fd00::1/64\r\nListenPort=51820\r\n", data);
+
+    assert_eq!(model, serde_ini::from_str::<TestModel>(&data).unwrap());
+}
+
+#[test]
+fn seq_en_nested() {
+    #[derive(Serialize)]
+    struct Nested {
+        a: Vec<Vec<u32>>,
+    }
+
+    let e = serde_ini::to_string(&Nested { a: vec![vec![1, 2], vec![3]] }).unwrap_err();
+    match e {
+        serde_ini::ser::Error::UnsupportedType(serde_ini::ser::UnsupportedType::Seq) => (),
+        e => panic!("unexpected error {:?}", e),
+    }
+}
+
+#[test]
+fn seq_en_empty() {
+    #[derive(Deserialize, Serialize, PartialEq, Debug)]
+    struct Required {
+        a: Vec<u32>,
+    }
+
+    // an empty sequence writes no key, which only a defaulted field can read back
+    let data = serde_ini::to_string(&Required { a: Vec::new() }).unwrap();
+    assert_eq!("", data);
+    let e = serde_ini::from_str::<Required>(&data).unwrap_err();
+    match *e.kind() {
+        serde_ini::de::ErrorKind::MissingField("a") => (),
+        ref kind => panic!("unexpected error {:?}", kind),
+    }
+
+    let model = TestModel { include: Vec::new(), interface: Interface { address: Vec::new(), listen_port: 1 } };
+    let data = serde_ini::to_string(&model).unwrap();
+    assert_eq!("[Interface]\r\nListenPort=1\r\n", data);
+    assert_eq!(model, serde_ini::from_str::<TestModel>(&data).unwrap());
+}
@@ -17,7 +17,7 @@ struct TestModel {
     map2: Option<Box<TestModel>>,
 }
 
-const TEST_INPUT: &'static str = "
+const TEST_INPUT: &str = "
 ; Ignored comment
 key1=value1
 key2=255
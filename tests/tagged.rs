@@ -0,0 +1,166 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_ini;
+
+use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use serde_ini::{Deserializer, Serializer, Writer, LineEnding};
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+enum Backend {
+    File { path: String, mode: String },
+    Http { url: String },
+    Null,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+#[serde(tag = "t", content = "c")]
+enum Source {
+    Local { path: String, depth: u8 },
+    Named(String),
+    Stdin,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+enum Cache {
+    Memory { size: u32 },
+    Disk(String),
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+struct Storage {
+    cache: Cache,
+    spill: Cache,
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+struct TestModel {
+    primary: Backend,
+    fallback: Backend,
+    none: Backend,
+    input: Source,
+    other: Source,
+    stdin: Source,
+    storage: Storage,
+}
+
+const TEST_INPUT: &str = "
+[primary]
+path=/var/db
+kind=file
+mode=rw
+
+[fallback]
+url=http://example.com
+kind=http
+
+[none]
+kind=null
+
+[input]
+t=Local
+c.path=/srv
+c.depth=2
+
+[other]
+t=Named
+c=remote
+
+[stdin]
+t=Stdin
+
+[storage]
+cache.Memory.size=64
+spill.Disk=/tmp
+";
+
+fn expected() -> TestModel {
+    TestModel {
+        primary: Backend::File {
+            path: "/var/db".into(),
+            mode: "rw".into(),
+        },
+        fallback: Backend::Http {
+            url: "http://example.com".into(),
+        },
+        none: Backend::Null,
+        input: Source::Local {
+            path: "/srv".into(),
+            depth: 2,
+        },
+        other: Source::Named("remote".into()),
+        stdin: Source::Stdin,
+        storage: Storage {
+            cache: Cache::Memory {
+                size: 64,
+            },
+            spill: Cache::Disk("/tmp".into()),
+        },
+    }
+}
+
+#[test]
+fn tagged_de() {
+    let mut de = Deserializer::from_str(TEST_INPUT).key_separator('.');
+    assert_eq!(expected(), TestModel::deserialize(&mut de).unwrap());
+}
+
+#[test]
+fn tagged_en() {
+    let model = expected();
+
+    let mut data = Vec::new();
+    model.serialize(&mut Serializer::new(Writer::new(&mut data, LineEnding::Linefeed)).key_separator('.')).unwrap();
+    assert_eq!("[primary]\nkind=file\npath=/var/db\nmode=rw\n\
+        [fallback]\nkind=http\nurl=http://example.com\n\
+        [none]\nkind=null\n\
+        [input]\nt=Local\nc.path=/srv\nc.depth=2\n\
+        [other]\nt=Named\nc=remote\n\
+        [stdin]\nt=Stdin\n\
+        [storage]\ncache.Memory.size=64\nspill.Disk=/tmp\n", String::from_utf8(data.clone()).unwrap());
+
+    let mut de = Deserializer::from_read(&data[..]).key_separator('.');
+    assert_eq!(model, TestModel::deserialize(&mut de).unwrap());
+}
+
+#[test]
+fn tagged_limits() {
+    #[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+    #[serde(tag = "kind", rename_all = "lowercase")]
+    enum Typed {
+        File { path: String, size: u32 },
+    }
+
+    #[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+    struct Model {
+        backend: Typed,
+    }
+
+    // an internally tagged variant is buffered without its field types, so its values are strings
+    const INPUT: &str = "[backend]\nkind=file\npath=/var/db\nsize=10\n";
+    let e = serde_ini::from_str::<Model>(INPUT).unwrap_err();
+    assert_eq!("in [backend] size = \"10\": invalid value: string \"10\", expected u32 at line 4, column 1", e.to_string());
+
+    // inference reads numbers, but then a string field cannot hold a value that looks like one
+    let mut de = Deserializer::from_str(INPUT).infer_types(true);
+    assert_eq!(Typed::File { path: "/var/db".into(), size: 10 }, Model::deserialize(&mut de).unwrap().backend);
+    let mut de = Deserializer::from_str("[backend]\nkind=file\npath=123\nsize=10\n").infer_types(true);
+    assert!(Model::deserialize(&mut de).is_err());
+
+    // the content of an adjacently tagged struct variant is a map, which needs a key or section
+    // separator to be written
+    let mut map = BTreeMap::new();
+    map.insert("input", Source::Local { path: "/srv".into(), depth: 2 });
+    match serde_ini::to_string(&map) {
+        Err(serde_ini::ser::Error::UnsupportedType(serde_ini::ser::UnsupportedType::Map)) => (),
+        result => panic!("unexpected {:?}", result),
+    }
+
+    let mut data = Vec::new();
+    map.serialize(&mut Serializer::new(Writer::new(&mut data, LineEnding::Linefeed)).section_separator('.')).unwrap();
+    assert_eq!("[input]\nt=Local\n[input.c]\npath=/srv\ndepth=2\n", String::from_utf8(data.clone()).unwrap());
+    let mut de = Deserializer::from_read(&data[..]).section_separator('.');
+    assert_eq!(map["input"], BTreeMap::<String, Source>::deserialize(&mut de).unwrap()["input"]);
+}
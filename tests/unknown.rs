@@ -0,0 +1,112 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_ini;
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::rc::Rc;
+use serde::Deserialize;
+use serde_ini::{Deserializer, Position};
+use serde_ini::de::{ErrorKind, UnknownItem};
+
+#[derive(Deserialize, Clone, PartialEq, Default, Debug)]
+struct Pool {
+    max: u32,
+}
+
+#[derive(Deserialize, Clone, PartialEq, Default, Debug)]
+struct Server {
+    listen_port: u16,
+    #[serde(default)]
+    pool: Pool,
+    #[serde(default)]
+    env: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize, Clone, PartialEq, Default, Debug)]
+struct TestModel {
+    name: String,
+    server: Server,
+}
+
+const TEST_INPUT: &str = "\
+name=app
+nmae=typo
+[server]
+listen_port=80
+lisen_port=8080
+pool.max=5
+pool.mx=6
+env.ANYTHING=goes
+[sevrer]
+listen_port=81
+[other]
+";
+
+fn item(section: Option<&str>, key: Option<&str>, line: usize, expected: &'static [&'static str], suggestion: Option<&'static str>) -> UnknownItem {
+    UnknownItem {
+        section: section.map(Into::into),
+        key: key.map(Into::into),
+        position: Some(Position { line, column: 1 }),
+        expected,
+        suggestion,
+    }
+}
+
+const TOP: &[&str] = &["name", "server"];
+const SERVER: &[&str] = &["listen_port", "pool", "env"];
+const POOL: &[&str] = &["max"];
+
+#[test]
+fn unknown_report() {
+    let mut reported = Vec::new();
+    let mut de = Deserializer::from_str(TEST_INPUT).key_separator('.')
+        .report_unknown(|items| reported.extend(items));
+    let model = TestModel::deserialize(&mut de).unwrap();
+    assert_eq!((80, 5), (model.server.listen_port, model.server.pool.max));
+    drop(de);
+
+    assert_eq!(vec![
+        item(None, Some("nmae"), 2, TOP, Some("name")),
+        item(Some("server"), Some("lisen_port"), 5, SERVER, Some("listen_port")),
+        item(Some("server"), Some("pool.mx"), 7, POOL, Some("max")),
+        item(Some("sevrer"), None, 9, TOP, Some("server")),
+        item(Some("other"), None, 11, TOP, None),
+    ], reported);
+
+    // nothing is reported for a document without unknown items, and the callback can be shared
+    let reported = Rc::new(RefCell::new(0));
+    let sink = reported.clone();
+    let mut de = Deserializer::from_str("name=app\n[server]\nlisten_port=80\n")
+        .report_unknown(move |_| *sink.borrow_mut() += 1);
+    TestModel::deserialize(&mut de).unwrap();
+    assert_eq!(0, *reported.borrow());
+}
+
+#[test]
+fn unknown_deny() {
+    let mut de = Deserializer::from_str("name=app\n[server]\nlisten_port=80\nlisen_port=8080\n").deny_unknown();
+    let error = TestModel::deserialize(&mut de).unwrap_err();
+    match *error.kind() {
+        ErrorKind::UnknownField { ref field, expected, suggestion } =>
+            assert_eq!(("lisen_port", SERVER, Some("listen_port")), (&field[..], expected, suggestion)),
+        ref kind => panic!("unexpected {:?}", kind),
+    }
+    assert_eq!("in [server] lisen_port: unknown field `lisen_port`, did you mean `listen_port`? at line 4, column 1", error.to_string());
+
+    let mut de = Deserializer::from_str("name=app\n[server]\nlisten_port=80\n[x]\n").deny_unknown();
+    let error = TestModel::deserialize(&mut de).unwrap_err();
+    assert_eq!("in [x]: unknown field `x`, expected one of `name`, `server` at line 4, column 1", error.to_string());
+
+    // maps accept any key
+    let mut de = Deserializer::from_str("[a]\nb=c\n").deny_unknown();
+    BTreeMap::<String, BTreeMap<String, String>>::deserialize(&mut de).unwrap();
+
+    // every unknown item is reported when collecting errors
+    let mut de = Deserializer::from_str(TEST_INPUT).key_separator('.').deny_unknown();
+    let (model, errors) = de.deserialize_collecting::<TestModel>();
+    assert!(model.is_some());
+    let lines: Vec<_> = errors.iter().map(|e| e.line().unwrap()).collect();
+    assert_eq!(vec![2, 5, 7, 9, 11], lines);
+}
@@ -0,0 +1,115 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_ini;
+
+use std::io;
+use std::collections::BTreeMap;
+use serde_ini::{Serializer, Writer, Document};
+use serde_ini::parse::SyntaxError;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Default, Debug)]
+struct Server {
+    host: String,
+    port: u16,
+    #[serde(default)]
+    allow: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Default, Debug)]
+struct TestModel {
+    name: String,
+    server: Server,
+}
+
+const TEST_INPUT: &str = "\
+; app settings
+name = app
+
+[server]
+host = localhost ; default
+port = 80
+allow=a
+allow=b
+legacy=1
+
+# unused
+[old]
+x=1
+";
+
+fn load() -> (Document, TestModel) {
+    (TEST_INPUT.parse().unwrap(), serde_ini::from_str(TEST_INPUT).unwrap())
+}
+
+#[test]
+fn update_unchanged() {
+    let (mut doc, model) = load();
+    serde_ini::to_document(&mut doc, &model).unwrap();
+    assert_eq!(TEST_INPUT, doc.to_string());
+}
+
+#[test]
+fn update_values() {
+    let (mut doc, mut model) = load();
+    model.server.port = 8080;
+    model.server.allow = vec!["a".into(), "c".into(), "d".into()];
+    serde_ini::to_document(&mut doc, &model).unwrap();
+    assert_eq!(TEST_INPUT
+        .replace("port = 80", "port = 8080")
+        .replace("allow=b", "allow=c\nallow=d"), doc.to_string());
+
+    model.server.allow = vec!["a".into()];
+    serde_ini::to_document(&mut doc, &model).unwrap();
+    assert_eq!(TEST_INPUT
+        .replace("port = 80", "port = 8080")
+        .replace("allow=b\n", ""), doc.to_string());
+}
+
+#[test]
+fn update_new_keys() {
+    let mut doc = "; comment\n[server]\nhost=h\n\n; trailing\n".parse::<Document>().unwrap();
+    serde_ini::to_document(&mut doc, &TestModel {
+        name: "app".into(),
+        server: Server { host: "h".into(), port: 1, allow: vec!["x".into()] },
+    }).unwrap();
+    assert_eq!("; comment\nname=app\n[server]\nhost=h\nport=1\nallow=x\n\n; trailing\n", doc.to_string());
+}
+
+#[test]
+fn update_remove_missing() {
+    let (mut doc, model) = load();
+    Serializer::new(Writer::new(io::sink(), Default::default()))
+        .remove_missing(true)
+        .update_document(&mut doc, &model).unwrap();
+    assert_eq!("\
+; app settings
+name = app
+
+[server]
+host = localhost ; default
+port = 80
+allow=a
+allow=b
+
+# unused
+", doc.to_string());
+}
+
+#[test]
+fn update_unwritable() {
+    let (mut doc, mut model) = load();
+    model.server.host = "local\nhost".into();
+    match serde_ini::to_document(&mut doc, &model) {
+        Err(serde_ini::ser::Error::Syntax(SyntaxError::LineBreak)) => (),
+        r => panic!("expected a line break error, got {:?}", r),
+    }
+
+    let mut map = BTreeMap::new();
+    map.insert("a=b", "c");
+    match serde_ini::to_document(&mut doc, &map) {
+        Err(serde_ini::ser::Error::Syntax(SyntaxError::KeyName)) => (),
+        r => panic!("expected a key name error, got {:?}", r),
+    }
+    assert_eq!(TEST_INPUT, doc.to_string());
+}
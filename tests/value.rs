@@ -0,0 +1,75 @@
+#[macro_use]
+extern crate serde_derive;
+extern crate serde;
+extern crate serde_ini;
+
+use serde_ini::Value;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Default, Debug)]
+struct Server {
+    port: u16,
+    allow: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Default, Debug)]
+struct TestModel {
+    name: String,
+    server: Server,
+}
+
+const TEST_INPUT: &str = "name=app\r\n[server]\r\nport=80\r\nallow=a\r\nallow=b\r\n[plugin]\r\nmode=fast\r\n";
+
+fn value() -> Value {
+    Value {
+        global: vec![("name".into(), "app".into())],
+        sections: vec![
+            ("server".into(), vec![
+                ("port".into(), "80".into()),
+                ("allow".into(), "a".into()),
+                ("allow".into(), "b".into()),
+            ]),
+            ("plugin".into(), vec![("mode".into(), "fast".into())]),
+        ],
+    }
+}
+
+#[test]
+fn value_de() {
+    assert_eq!(value(), serde_ini::from_str::<Value>(TEST_INPUT).unwrap());
+}
+
+#[test]
+fn value_en() {
+    assert_eq!(TEST_INPUT, serde_ini::to_string(&value()).unwrap());
+}
+
+#[test]
+fn value_convert() {
+    let model = TestModel {
+        name: "app".into(),
+        server: Server { port: 80, allow: vec!["a".into(), "b".into()] },
+    };
+    let mut value = value();
+    value.sections.pop();
+    assert_eq!(value, serde_ini::to_value(&model).unwrap());
+    assert_eq!(model, serde_ini::from_value::<TestModel>(value.clone()).unwrap());
+
+    value.set(Some("server"), "port", "x");
+    let error = serde_ini::from_value::<TestModel>(value).unwrap_err();
+    assert_eq!((Some("server"), Some("port")), (error.section(), error.key()));
+}
+
+#[test]
+fn value_edit() {
+    let mut value = value();
+    assert_eq!(Some("80"), value.get(Some("server"), "port"));
+    assert_eq!(Some("app"), value.get(None, "name"));
+    assert_eq!(None, value.get(Some("missing"), "port"));
+
+    assert_eq!(Some("a".into()), value.set(Some("server"), "allow", "c"));
+    assert_eq!(None, value.set(Some("cache"), "size", "10"));
+    assert_eq!(Some("fast".into()), value.remove(Some("plugin"), "mode"));
+    assert_eq!(None, value.remove(Some("missing"), "mode"));
+    assert_eq!(Some(&[("size".into(), "10".into())][..]), value.section("cache"));
+    assert_eq!("name=app\r\n[server]\r\nport=80\r\nallow=c\r\n[plugin]\r\n[cache]\r\nsize=10\r\n", serde_ini::to_string(&value).unwrap());
+}